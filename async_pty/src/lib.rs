@@ -1,7 +1,7 @@
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, Mutex};
 
 use anyhow::Result;
-use portable_pty::{CommandBuilder, PtySize, native_pty_system};
+use portable_pty::{ChildKiller, CommandBuilder, MasterPty, PtySize, native_pty_system};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
@@ -20,9 +20,48 @@ pub enum TerminalInput {
     Resize(TerminalSize),
 }
 
-#[derive(Debug)]
 pub struct PtyProcess {
     write: mpsc::Sender<TerminalInput>,
+    /// Used to look up the foreground process, `None` without a pty
+    master: Option<Arc<Mutex<Box<dyn MasterPty + Send>>>>,
+    /// The started program
+    pid: Option<u32>,
+    /// Hangs up the program once dropped, `None` without a pty
+    killer: Option<Box<dyn ChildKiller + Send + Sync>>,
+    /// Cancelled once the program exited
+    exited: CancellationToken,
+}
+
+impl std::fmt::Debug for PtyProcess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PtyProcess")
+            .field("pid", &self.pid)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The program started inside the pty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Command {
+    /// Falls back to the default shell of the user
+    pub program: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Starts the default shell as a login shell, which sources the profile files.
+    /// Only applies on Unix when neither the program nor arguments are given.
+    #[serde(default)]
+    pub login: bool,
+}
+
+impl Command {
+    /// File name of the program, or of the default shell
+    pub fn program_name(&self) -> String {
+        let program = self.program.as_deref().unwrap_or(&SHELL);
+        std::path::Path::new(program).file_stem().map_or_else(
+            || program.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,23 +82,61 @@ impl From<TerminalSize> for PtySize {
 
 impl PtyProcess {
     pub async fn shell(size: TerminalSize) -> Result<(Self, mpsc::Receiver<Vec<u8>>)> {
+        Self::spawn(Command::default(), size).await
+    }
+
+    /// A process without a pty, e.g. to test a terminal without running a program.
+    /// Everything written to it ends up in the returned receiver.
+    pub fn detached() -> (Self, mpsc::Receiver<TerminalInput>) {
+        let (write, receiver) = mpsc::channel(100);
+        (
+            Self {
+                write,
+                master: None,
+                pid: None,
+                killer: None,
+                exited: CancellationToken::new(),
+            },
+            receiver,
+        )
+    }
+
+    pub async fn spawn(
+        command: Command,
+        size: TerminalSize,
+    ) -> Result<(Self, mpsc::Receiver<Vec<u8>>)> {
         tokio::task::spawn_blocking(move || {
             let pty_system = native_pty_system();
 
             let pair = pty_system.openpty(size.into())?;
 
-            let shell_cmd = CommandBuilder::new(SHELL.to_owned());
+            let cmd = if command.login
+                && cfg!(unix)
+                && command.program.is_none()
+                && command.args.is_empty()
+            {
+                // the default program is started as a login shell, with `-` prepended to argv[0]
+                CommandBuilder::new_default_prog()
+            } else {
+                let mut cmd =
+                    CommandBuilder::new(command.program.unwrap_or_else(|| SHELL.to_owned()));
+                cmd.args(command.args);
+                cmd
+            };
 
-            let child = pair.slave.spawn_command(shell_cmd)?;
+            let child = pair.slave.spawn_command(cmd)?;
+            let pid = child.process_id();
+            let killer = child.clone_killer();
             drop(pair.slave);
 
-            let master = pair.master;
-            let mut reader = master.try_clone_reader()?;
+            let master = Arc::new(Mutex::new(pair.master));
+            let mut reader = master.lock().unwrap().try_clone_reader()?;
             let (writer_send, writer_recv) = mpsc::channel::<TerminalInput>(10);
             let (helper_send, helper_recv) = mpsc::channel::<TerminalInput>(10);
 
             let cancel = CancellationToken::new();
             let cancel2 = cancel.clone();
+            let exited = cancel.clone();
 
             tokio::spawn(async move {
                 let cancel = cancel2;
@@ -76,9 +153,12 @@ impl PtyProcess {
             });
 
             // writer thread
+            let writer_master = Arc::clone(&master);
             std::thread::spawn(move || {
+                let master = writer_master;
                 let mut helper_recv = helper_recv;
-                if let Ok(mut writer) = master.take_writer() {
+                let writer = master.lock().unwrap().take_writer();
+                if let Ok(mut writer) = writer {
                     while let Some(input) = helper_recv.blocking_recv() {
                         match input {
                             TerminalInput::Input(input) => {
@@ -87,7 +167,7 @@ impl PtyProcess {
                                 }
                             }
                             TerminalInput::Resize(size) => {
-                                if let Err(_err) = master.resize(size.into()) {
+                                if let Err(_err) = master.lock().unwrap().resize(size.into()) {
                                     return;
                                 }
                             }
@@ -132,11 +212,40 @@ impl PtyProcess {
                 cancel.cancel();
             });
 
-            Ok((Self { write: writer_send }, reader_recv))
+            Ok((
+                Self {
+                    write: writer_send,
+                    master: Some(master),
+                    pid,
+                    killer: Some(killer),
+                    exited,
+                },
+                reader_recv,
+            ))
         })
         .await?
     }
 
+    /// Whether another program than the started one runs in the foreground,
+    /// e.g. an editor started from the shell. Always `false` without job control.
+    pub fn runs_foreground_job(&self) -> bool {
+        #[cfg(unix)]
+        {
+            let (Some(master), Some(pid)) = (&self.master, self.pid) else {
+                return false;
+            };
+            master
+                .lock()
+                .ok()
+                .and_then(|master| master.process_group_leader())
+                .is_some_and(|leader| leader as u32 != pid)
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
+
     pub async fn resize(&self, size: TerminalSize) -> Result<()> {
         self.write.send(TerminalInput::Resize(size)).await?;
 
@@ -161,3 +270,16 @@ impl PtyProcess {
         Ok(())
     }
 }
+
+/// The reader thread keeps the pty open while it waits for output,
+/// so the program has to be hung up explicitly, like closing the pty would do.
+impl Drop for PtyProcess {
+    fn drop(&mut self) {
+        if let Some(mut killer) = self.killer.take()
+            && !self.exited.is_cancelled()
+        {
+            // SIGHUP on unix
+            let _ = killer.kill();
+        }
+    }
+}
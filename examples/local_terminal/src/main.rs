@@ -44,6 +44,10 @@ impl UI {
                     local_terminal::Action::Run(task) => task.map(Message::Terminal),
                     local_terminal::Action::IdChanged => Task::none(),
                     local_terminal::Action::Close => iced::exit(),
+                    local_terminal::Action::KeyPressed(_) => Task::none(),
+                    local_terminal::Action::CtrlClick { .. } => Task::none(),
+                    local_terminal::Action::UrlPasted(_) => Task::none(),
+                    local_terminal::Action::Bell => Task::none(),
                     local_terminal::Action::None => Task::none(),
                 }
             }
@@ -0,0 +1,118 @@
+//! Command line handling.
+//!
+//! Without arguments frostbyte starts, everything else either prints information,
+//! runs a program without a window or is forwarded to the running instance via [`ipc`](crate::ipc).
+
+use crate::{config::Config, ipc};
+
+const COMMANDS: &[&str] = &["send", "keys", "record", "focus-tab"];
+
+/// Handles the arguments, returns the exit code if frostbyte shouldn't start.
+pub fn handle(args: &[String]) -> Option<i32> {
+    let first = args.first()?;
+
+    match first.as_str() {
+        "-h" | "--help" => {
+            println!("{}", help());
+            Some(0)
+        }
+        "-V" | "--version" => {
+            println!("frostbyte_term {}", env!("CARGO_PKG_VERSION"));
+            Some(0)
+        }
+        "--dump" => Some(dump(&args[1..])),
+        command if COMMANDS.contains(&command) => Some(ipc::run_client(args)),
+        other => {
+            eprintln!("unknown argument: {}\n\n{}", other, ipc::USAGE);
+            eprintln!("\nsee --help for more information");
+            Some(2)
+        }
+    }
+}
+
+fn help() -> String {
+    let config_path = Config::path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "unavailable".to_string());
+
+    format!(
+        "frostbyte_term {version}
+A dropdown terminal, toggled with a global hotkey, the tray icon or SIGUSR1.
+
+Starts frostbyte when run without arguments.
+
+{usage}
+
+options:
+  -h, --help                   print this help
+  -V, --version                print the version
+  --dump <program> [args...]   run a program in an {cols}x{rows} terminal and print its final screen
+
+config file: {config_path}
+  The config covers the appearance (colors, opacity, background image, letter spacing),
+  tab behavior (close button, last tab, profiles, startup command), the fallback hotkey
+  and scrollback logs. See the Readme for all keys.",
+        version = env!("CARGO_PKG_VERSION"),
+        usage = ipc::USAGE,
+        cols = DUMP_SIZE.cols,
+        rows = DUMP_SIZE.rows,
+    )
+}
+
+/// Size of the terminal programs run in by `--dump`
+const DUMP_SIZE: frozen_term::Size = frozen_term::Size { cols: 80, rows: 24 };
+
+/// Runs the program in a terminal without a window and prints the screen once it exits.
+/// Queries of the program aren't answered.
+fn dump(command: &[String]) -> i32 {
+    let [program, args @ ..] = command else {
+        eprintln!("--dump needs a program to run");
+        return 2;
+    };
+    let command = async_pty::Command {
+        program: Some(program.clone()),
+        args: args.to_vec(),
+        login: false,
+    };
+
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("Error starting runtime: {}", err);
+            return 1;
+        }
+    };
+
+    let screen = runtime.block_on(async {
+        let size = async_pty::TerminalSize {
+            cols: DUMP_SIZE.cols as u16,
+            rows: DUMP_SIZE.rows as u16,
+        };
+        let (_process, mut output) = async_pty::PtyProcess::spawn(command, size)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let (mut terminal, _) = frozen_term::Terminal::new();
+        let _ = terminal.resize(DUMP_SIZE);
+        // ends once the program exited
+        while let Some(chunk) = output.recv().await {
+            terminal.advance_bytes(chunk);
+        }
+
+        Ok::<_, String>(terminal.screen_contents())
+    });
+
+    match screen {
+        Ok(screen) => {
+            println!("{}", screen.trim_end());
+            0
+        }
+        Err(err) => {
+            eprintln!("Error running {}: {}", program, err);
+            1
+        }
+    }
+}
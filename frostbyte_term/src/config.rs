@@ -0,0 +1,838 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::hotkey::Hotkey;
+
+/// User configuration, read from `config.toml` in the frostbyte config directory.
+///
+/// Every field is optional in the file, missing values fall back to their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Streams the raw output of every tab into a log file in the runtime directory.
+    pub scrollback_log: bool,
+    /// Size in bytes after which a scrollback log gets rotated.
+    pub scrollback_log_max_size: u64,
+    /// Pixels the window grows or shrinks by with Ctrl+Alt+ArrowUp/ArrowDown.
+    pub height_step: f32,
+    pub on_last_tab_close: LastTabClose,
+    /// Message shown while there are no tabs, next to a New Tab button.
+    pub empty_state_message: String,
+    /// Additional space between characters in pixels, clamped to `-5.0..=20.0`.
+    pub letter_spacing: f32,
+    /// Draws bold text in the basic colors with their bright variants instead of a bold font.
+    /// Bold text in any other color is always drawn with a bold font.
+    pub bold_is_bright: bool,
+    /// Contrast ratio text keeps to its background, `1.0` is off and `4.5` matches WCAG AA.
+    pub minimum_contrast: f32,
+    /// Asks for confirmation before quitting while multiple tabs are open or a program runs in one,
+    /// and before closing all tabs while a program runs in one.
+    pub confirm_quit: bool,
+    /// Hex color like `"#ffffff"`
+    pub cursor_color: Option<String>,
+    /// Hex color of the character below a block cursor, defaults to the background color.
+    pub cursor_text_color: Option<String>,
+    /// Color theme of the terminals, one of the builtin ones or of `themes`.
+    pub theme: Option<String>,
+    /// Themes the theme shortcut switches through, `"default"` for the default colors.
+    pub theme_cycle: Vec<String>,
+    /// Additional themes by name.
+    pub themes: BTreeMap<String, Theme>,
+    pub background_image: Option<PathBuf>,
+    pub background_image_mode: WallpaperMode,
+    pub background_image_opacity: f32,
+    /// Alpha of a black overlay drawn above the background image to keep the text readable.
+    pub background_overlay_alpha: f32,
+    /// Opacity of the window background, requires a compositor.
+    pub opacity: f32,
+    /// Opacity while the window isn't focused, defaults to `opacity`.
+    pub unfocused_opacity: Option<f32>,
+    /// Darkens the terminal while the window isn't focused, from `0.0` (off) to `1.0`.
+    pub unfocused_dim: f32,
+    /// Asks the compositor to blur the background behind the translucent window.
+    pub blur: bool,
+    /// Where the window is placed on its monitor, `"center"` or `{ x, y }` in pixels.
+    pub position: WindowPosition,
+    /// Starts in the background, only the tray icon and the global hotkey open the window.
+    pub start_hidden: bool,
+    /// Hides the window once it loses focus, unless it is pinned.
+    pub hide_on_focus_loss: bool,
+    /// How long the focus has to stay away before the window is hidden.
+    pub focus_loss_hide_delay_ms: u64,
+    /// Keeps the window out of the taskbar and the window switcher, only supported on Windows.
+    pub skip_taskbar: bool,
+    /// Shows the tray icon, without it the window is only opened by the hotkey, SIGUSR1 or the socket.
+    pub tray: bool,
+    /// Global hotkey to register instead if the default one is already taken,
+    /// one of `"f12"`, `"alt_f12"` or `"pause"`.
+    pub fallback_hotkey: Option<Hotkey>,
+    /// Additional global hotkeys, each bound to an action.
+    pub hotkeys: Vec<HotkeyBinding>,
+    /// Tabs opened when the window opens without any tabs, at most 10.
+    pub initial_tabs: usize,
+    /// New tabs are refused while this many tabs are open.
+    pub max_tabs: Option<usize>,
+    /// Command that is run in the first tab after the shell has started.
+    pub startup_command: Option<String>,
+    /// Tab title while the program hasn't set one.
+    pub title_source: TitleSource,
+    pub tab_close_button: TabCloseButton,
+    /// What the X button of the window does, also applies to close requests like Alt+F4.
+    pub window_close_button: WindowCloseButton,
+    pub tab_label: TabLabel,
+    /// Glyphs shown by `tab_label = "icon"` by program name, on top of the builtin ones.
+    pub tab_icons: BTreeMap<String, String>,
+    pub tab_bar: TabBar,
+    /// Switches tabs with the mouse wheel over the tab bar, as long as all tabs fit into it.
+    pub tabbar_scroll_switches: bool,
+    pub backspace_sends: BackspaceSends,
+    /// Held with PageUp and PageDown to scroll, without it the keys reach the program.
+    pub scrollback_modifier: ScrollbackModifier,
+    /// Sent by Shift+Enter instead of a regular Enter, e.g. `"\n"`.
+    pub shift_enter: Option<String>,
+    /// Sent by Alt+Enter instead of a regular Enter.
+    pub alt_enter: Option<String>,
+    /// What happens when the pasted text is a single URL.
+    pub url_paste_action: UrlPasteAction,
+    /// What happens when a program rings the bell.
+    pub bell: BellAction,
+    /// Passes the Ctrl+Shift and Ctrl+Alt shortcuts to programs started from the shell instead,
+    /// e.g. to a nested terminal multiplexer. Requires job control, i.e. not on Windows.
+    pub pass_shortcuts_to_programs: bool,
+    /// Lets applications set the clipboard via OSC 52, e.g. vim or tmux over ssh.
+    pub allow_osc52_write: bool,
+    /// How often per second new output is processed and drawn.
+    pub max_fps: u32,
+    /// How many bytes of output are processed at once before handling input again.
+    pub parse_budget: usize,
+    /// Lines of output kept per tab, full screen programs don't add to them.
+    pub scrollback_lines: usize,
+    /// Shows typed characters before the process echoed them, e.g. for slow ssh connections.
+    pub local_echo: bool,
+    /// Starts shells as login shells, so they source `.profile` or `.bash_profile`.
+    /// Defaults to `true` on macOS, where GUI apps don't inherit the login environment.
+    pub login_shell: bool,
+    /// Keeps tabs open after their shell exited, showing a restart button.
+    pub keep_on_exit: bool,
+    /// Restarts shells once they exit, configured in the `[auto_restart]` table.
+    pub auto_restart: Option<AutoRestart>,
+    /// Command of the "Attach tmux Session" tray item, split at whitespace.
+    pub tmux_command: String,
+    /// Directory recordings are saved to, defaults to the home directory.
+    pub recording_dir: Option<PathBuf>,
+    /// Names of the broadcast groups, tabs are assigned to them with a right click.
+    /// Keys typed into a tab are also typed into the other tabs of its group.
+    pub tab_groups: Vec<String>,
+    /// Additional programs offered in the dropdown of the New Tab button.
+    pub profiles: Vec<Profile>,
+    /// Patterns which run a command when Ctrl+clicked.
+    pub smart_links: Vec<SmartLink>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Overrides `scrollback_log` for tabs of this profile, e.g. to not log a password manager
+    #[serde(default)]
+    pub scrollback_log: Option<bool>,
+}
+
+impl Profile {
+    pub fn command(&self) -> async_pty::Command {
+        async_pty::Command {
+            program: Some(self.command.clone()),
+            args: self.args.clone(),
+            login: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "SmartLinkConfig")]
+pub struct SmartLink {
+    /// Compiled once, an invalid regex makes the whole config invalid
+    pub regex: regex::Regex,
+    /// Split at whitespace, `$1` or `${name}` in the arguments are replaced by the captures.
+    pub command: String,
+}
+
+/// A smart link as written in the config
+#[derive(Deserialize)]
+struct SmartLinkConfig {
+    regex: String,
+    command: String,
+}
+
+impl TryFrom<SmartLinkConfig> for SmartLink {
+    type Error = String;
+
+    fn try_from(value: SmartLinkConfig) -> Result<Self, Self::Error> {
+        let regex = regex::Regex::new(&value.regex)
+            .map_err(|err| format!("invalid smart link regex {}: {}", value.regex, err))?;
+        Ok(Self {
+            regex,
+            command: value.command,
+        })
+    }
+}
+
+impl SmartLink {
+    /// The command for the match at `column` of `line`, if any.
+    ///
+    /// Captures only ever end up in single arguments and are never run through a shell.
+    pub fn command_at(&self, line: &str, column: usize) -> Option<std::process::Command> {
+        let offset = line
+            .char_indices()
+            .nth(column)
+            .map_or(line.len(), |(offset, _)| offset);
+        let captures = self.regex.captures_iter(line).find(|captures| {
+            captures
+                .get(0)
+                .is_some_and(|found| found.start() <= offset && offset < found.end())
+        })?;
+
+        let mut args = self.command.split_whitespace().map(|word| {
+            let mut arg = String::new();
+            captures.expand(word, &mut arg);
+            arg
+        });
+        let mut command = std::process::Command::new(args.next()?);
+        command.args(args);
+        Some(command)
+    }
+}
+
+/// Colors of a theme as hex strings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Theme {
+    pub foreground: String,
+    pub background: String,
+    /// The 8 basic and 8 bright colors, the rest of the 256 color palette stays the same.
+    pub palette: Vec<String>,
+}
+
+/// A theme with its colors parsed.
+pub struct ThemeColors {
+    pub foreground: iced::Color,
+    pub background: iced::Color,
+    pub palette: [iced::Color; 16],
+}
+
+impl ThemeColors {
+    fn parse(name: &str, foreground: &str, background: &str, palette: &[&str]) -> Option<Self> {
+        let key = format!("theme {}", name);
+        let foreground = parse_color(&key, Some(foreground))?;
+        let background = parse_color(&key, Some(background))?;
+        let Ok(palette) = <[&str; 16]>::try_from(palette) else {
+            eprintln!("The palette of theme {} needs 16 colors", name);
+            return None;
+        };
+        let mut colors = [iced::Color::BLACK; 16];
+        for (color, value) in colors.iter_mut().zip(palette) {
+            *color = parse_color(&key, Some(value))?;
+        }
+
+        Some(Self {
+            foreground,
+            background,
+            palette: colors,
+        })
+    }
+}
+
+/// Name, foreground, background and the 16 color palette
+type BuiltinTheme = (&'static str, &'static str, &'static str, [&'static str; 16]);
+
+const SOLARIZED_PALETTE: [&str; 16] = [
+    "#073642", "#dc322f", "#859900", "#b58900", "#268bd2", "#d33682", "#2aa198", "#eee8d5",
+    "#002b36", "#cb4b16", "#586e75", "#657b83", "#839496", "#6c71c4", "#93a1a1", "#fdf6e3",
+];
+
+const BUILTIN_THEMES: &[BuiltinTheme] = &[
+    (
+        "gruvbox",
+        "#ebdbb2",
+        "#282828",
+        [
+            "#282828", "#cc241d", "#98971a", "#d79921", "#458588", "#b16286", "#689d6a", "#a89984",
+            "#928374", "#fb4934", "#b8bb26", "#fabd2f", "#83a598", "#d3869b", "#8ec07c", "#ebdbb2",
+        ],
+    ),
+    ("solarized-dark", "#839496", "#002b36", SOLARIZED_PALETTE),
+    ("solarized-light", "#657b83", "#fdf6e3", SOLARIZED_PALETTE),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HotkeyBinding {
+    pub key: Hotkey,
+    #[serde(flatten)]
+    pub action: HotkeyAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Shows or hides the window, like the default hotkey
+    Toggle,
+    /// Shows the window on the given monitor, or hides it if it is already shown there
+    ToggleOnMonitor { monitor: usize },
+    /// Opens a tab running the profile of the given name
+    OpenProfile { profile: String },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WallpaperMode {
+    /// Covers the whole terminal, cropping the image if needed
+    #[default]
+    Fill,
+    /// Shows the whole image
+    Fit,
+    Tile,
+}
+
+/// Layout and colors of the tab bar, configured in the `[tab_bar]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TabBar {
+    pub height: f32,
+    /// Space between the tabs and buttons
+    pub spacing: f32,
+    pub tab_width: f32,
+    /// Width of the New Tab button
+    pub new_tab_width: f32,
+    /// Width of the monitor and close window buttons
+    pub button_width: f32,
+    pub close_button_width: f32,
+    /// Hex color of the tabs, defaults to the theme
+    pub color: Option<String>,
+    /// Hex color of the selected tab, defaults to the theme
+    pub selected_color: Option<String>,
+}
+
+impl Default for TabBar {
+    fn default() -> Self {
+        Self {
+            height: 40.0,
+            spacing: 5.0,
+            tab_width: 200.0,
+            new_tab_width: 200.0,
+            button_width: 40.0,
+            close_button_width: 30.0,
+            color: None,
+            selected_color: None,
+        }
+    }
+}
+
+/// Automatic restarts of exited shells, configured in the `[auto_restart]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AutoRestart {
+    /// Seconds before the first restart, doubled for shells exiting right after their start
+    pub delay: f32,
+    /// Shown while waiting for the restart
+    pub message: Option<String>,
+    /// Restarts of shells exiting right after their start, before giving up
+    pub max_attempts: u32,
+}
+
+impl Default for AutoRestart {
+    fn default() -> Self {
+        Self {
+            delay: 1.0,
+            message: None,
+            max_attempts: 5,
+        }
+    }
+}
+
+impl From<AutoRestart> for frozen_term::local_terminal::AutoRestart {
+    fn from(value: AutoRestart) -> Self {
+        Self {
+            delay: std::time::Duration::from_secs_f32(value.delay.max(0.0).min(60.0)),
+            message: value.message,
+            max_attempts: value.max_attempts,
+        }
+    }
+}
+
+impl TabBar {
+    pub fn color(&self) -> Option<iced::Color> {
+        parse_color("tab_bar.color", self.color.as_deref())
+    }
+
+    pub fn selected_color(&self) -> Option<iced::Color> {
+        parse_color("tab_bar.selected_color", self.selected_color.as_deref())
+    }
+}
+
+/// When the close button of a tab is shown.
+/// Tabs can always be closed with a middle click.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TabCloseButton {
+    #[default]
+    Always,
+    OnHover,
+    Never,
+}
+
+/// What the tabs in the tab bar show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TabLabel {
+    #[default]
+    Title,
+    /// Position in the tab bar, counted from 1
+    Index,
+    IndexTitle,
+    /// A glyph for the running program, see [`Config::tab_icon`]
+    Icon,
+}
+
+/// Nerd font glyphs of common programs
+const DEFAULT_TAB_ICONS: &[(&str, &str)] = &[
+    ("bash", "\u{f120}"),
+    ("zsh", "\u{f120}"),
+    ("fish", "\u{f120}"),
+    ("sh", "\u{f120}"),
+    ("pwsh", "\u{f120}"),
+    ("powershell", "\u{f120}"),
+    ("vim", "\u{e62b}"),
+    ("nvim", "\u{e62b}"),
+    ("ssh", "\u{f233}"),
+    ("htop", "\u{f080}"),
+    ("top", "\u{f080}"),
+    ("git", "\u{f1d3}"),
+    ("python", "\u{e73c}"),
+    ("python3", "\u{e73c}"),
+    ("tmux", "\u{f2d2}"),
+];
+/// Shown for programs without an icon
+const FALLBACK_TAB_ICON: &str = "\u{f120}";
+
+/// What a tab shows as its title while the program hasn't set one,
+/// `"program"`, `"cwd"`, `"shell"` or `"static:<text>"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TitleSource {
+    /// Whatever the terminal reports by default
+    #[default]
+    Program,
+    /// Name of the working directory reported by the shell via OSC 7,
+    /// or of the directory the shell was started in
+    Cwd,
+    /// Name of the shell or the program of the profile
+    Shell,
+    Static(String),
+}
+
+impl TryFrom<String> for TitleSource {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "program" => Ok(Self::Program),
+            "cwd" => Ok(Self::Cwd),
+            "shell" => Ok(Self::Shell),
+            _ => match value.strip_prefix("static:") {
+                Some(text) => Ok(Self::Static(text.to_string())),
+                None => Err(format!(
+                    "unknown title source {}, expected \"program\", \"cwd\", \"shell\" or \"static:<text>\"",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+impl TitleSource {
+    /// The default title of a tab running the command in the working directory, if known
+    pub fn title(&self, command: &async_pty::Command, cwd: Option<PathBuf>) -> Option<String> {
+        match self {
+            Self::Program => None,
+            Self::Cwd => {
+                // shells are started in the home directory
+                let cwd = cwd.or_else(home_dir)?;
+                let name = cwd.file_name().map_or_else(
+                    || cwd.to_string_lossy().into_owned(),
+                    |name| name.to_string_lossy().into_owned(),
+                );
+                Some(name)
+            }
+            Self::Shell => Some(command.program_name()),
+            Self::Static(text) => Some(text.clone()),
+        }
+    }
+}
+
+/// Modifier that makes PageUp and PageDown scroll.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollbackModifier {
+    #[default]
+    Shift,
+    Ctrl,
+    Alt,
+    /// Plain PageUp and PageDown scroll, they never reach the program
+    None,
+}
+
+impl From<ScrollbackModifier> for iced::keyboard::Modifiers {
+    fn from(value: ScrollbackModifier) -> Self {
+        match value {
+            ScrollbackModifier::Shift => Self::SHIFT,
+            ScrollbackModifier::Ctrl => Self::CTRL,
+            ScrollbackModifier::Alt => Self::ALT,
+            ScrollbackModifier::None => Self::empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackspaceSends {
+    #[default]
+    Del,
+    Bs,
+}
+
+impl From<BackspaceSends> for frozen_term::Backspace {
+    fn from(value: BackspaceSends) -> Self {
+        match value {
+            BackspaceSends::Del => Self::Del,
+            BackspaceSends::Bs => Self::Bs,
+        }
+    }
+}
+
+/// Position of the window relative to the top left corner of its monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum WindowPosition {
+    Named(NamedPosition),
+    Specific { x: f32, y: f32 },
+}
+
+impl Default for WindowPosition {
+    fn default() -> Self {
+        Self::Named(NamedPosition::Center)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamedPosition {
+    /// Centered horizontally at the top
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowCloseButton {
+    /// Hides the dropdown, like the global hotkey
+    #[default]
+    Hide,
+    /// Quits frostbyte, asking first if `confirm_quit` is set
+    Quit,
+}
+
+/// What happens when the pasted text is a single URL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlPasteAction {
+    #[default]
+    Paste,
+    /// Asks whether to open or paste it
+    Ask,
+    /// Opens it in the browser instead of pasting it
+    Open,
+}
+
+/// What happens when a program rings the bell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BellAction {
+    #[default]
+    None,
+    /// Asks the window manager to highlight the window while it isn't focused,
+    /// not supported on Wayland when running as a layer shell surface
+    Urgency,
+}
+
+/// What happens once the last tab has been closed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LastTabClose {
+    #[default]
+    CloseWindow,
+    /// Keeps the window open showing an empty state
+    KeepOpen,
+    Quit,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scrollback_log: false,
+            scrollback_log_max_size: 10 * 1024 * 1024,
+            height_step: 50.0,
+            on_last_tab_close: LastTabClose::default(),
+            empty_state_message: "No open tabs".to_string(),
+            letter_spacing: 0.0,
+            bold_is_bright: true,
+            minimum_contrast: 1.0,
+            confirm_quit: false,
+            cursor_color: None,
+            cursor_text_color: None,
+            theme: None,
+            theme_cycle: Vec::new(),
+            themes: BTreeMap::new(),
+            background_image: None,
+            background_image_mode: WallpaperMode::default(),
+            background_image_opacity: 1.0,
+            background_overlay_alpha: 0.5,
+            opacity: 1.0,
+            unfocused_opacity: None,
+            unfocused_dim: 0.0,
+            blur: false,
+            position: WindowPosition::default(),
+            start_hidden: true,
+            hide_on_focus_loss: false,
+            focus_loss_hide_delay_ms: 150,
+            skip_taskbar: true,
+            tray: true,
+            fallback_hotkey: None,
+            hotkeys: Vec::new(),
+            initial_tabs: 1,
+            max_tabs: None,
+            startup_command: None,
+            profiles: Vec::new(),
+            smart_links: Vec::new(),
+            tab_groups: vec!["red".to_string(), "green".to_string(), "blue".to_string()],
+            title_source: TitleSource::default(),
+            tab_close_button: TabCloseButton::default(),
+            window_close_button: WindowCloseButton::default(),
+            tab_label: TabLabel::default(),
+            tab_icons: BTreeMap::new(),
+            tab_bar: TabBar::default(),
+            tabbar_scroll_switches: true,
+            backspace_sends: BackspaceSends::default(),
+            scrollback_modifier: ScrollbackModifier::default(),
+            local_echo: false,
+            login_shell: cfg!(target_os = "macos"),
+            keep_on_exit: false,
+            auto_restart: None,
+            shift_enter: None,
+            alt_enter: None,
+            max_fps: 60,
+            parse_budget: 64 * 1024,
+            scrollback_lines: 3500,
+            pass_shortcuts_to_programs: false,
+            allow_osc52_write: false,
+            url_paste_action: UrlPasteAction::default(),
+            bell: BellAction::default(),
+            tmux_command: "tmux new -A -s main".to_string(),
+            recording_dir: None,
+        }
+    }
+}
+
+impl Config {
+    /// The glyph of the program a tab runs.
+    ///
+    /// The program is guessed from the first word of the title, which most programs set to their name.
+    /// Otherwise the program the tab was started with is used.
+    pub fn tab_icon(&self, title: &str, command: &async_pty::Command) -> &str {
+        let lookup = |program: &str| {
+            self.tab_icons.get(program).map(String::as_str).or_else(|| {
+                DEFAULT_TAB_ICONS
+                    .iter()
+                    .find(|(name, _)| *name == program)
+                    .map(|(_, icon)| *icon)
+            })
+        };
+
+        let title_program = title
+            .split_whitespace()
+            .next()
+            .and_then(|word| word.rsplit(['/', '\\']).next());
+        title_program
+            .and_then(lookup)
+            .or_else(|| lookup(&command.program_name()))
+            .unwrap_or(FALLBACK_TAB_ICON)
+    }
+
+    /// Loads the config file, falling back to the defaults if it is missing or invalid.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                eprintln!("Error reading config {}: {}", path.display(), err);
+                return Self::default();
+            }
+        };
+
+        match toml::from_str(&content) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("Error parsing config {}: {}", path.display(), err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn cursor_color(&self) -> Option<iced::Color> {
+        parse_color("cursor_color", self.cursor_color.as_deref())
+    }
+
+    pub fn cursor_text_color(&self) -> Option<iced::Color> {
+        parse_color("cursor_text_color", self.cursor_text_color.as_deref())
+    }
+
+    /// The colors of a theme from `themes` or a builtin one, `None` for `"default"` and unknown themes.
+    pub fn theme_colors(&self, name: &str) -> Option<ThemeColors> {
+        if let Some(theme) = self.themes.get(name) {
+            let palette: Vec<&str> = theme.palette.iter().map(String::as_str).collect();
+            return ThemeColors::parse(name, &theme.foreground, &theme.background, &palette);
+        }
+        if name == "default" {
+            return None;
+        }
+        match BUILTIN_THEMES.iter().find(|(builtin, ..)| *builtin == name) {
+            Some((_, foreground, background, palette)) => {
+                ThemeColors::parse(name, foreground, background, palette)
+            }
+            None => {
+                eprintln!("Unknown theme {}", name);
+                None
+            }
+        }
+    }
+
+    pub fn recording_dir(&self) -> PathBuf {
+        self.recording_dir
+            .clone()
+            .or_else(home_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("frostbyte").join("config.toml"))
+    }
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    let home = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var_os(home).map(PathBuf::from)
+}
+
+/// Directory for sockets and logs which don't need to survive a reboot.
+/// It is created if needed and only accessible by the current user.
+pub fn runtime_dir() -> std::io::Result<PathBuf> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(base) => PathBuf::from(base).join("frostbyte"),
+        // the temporary directory is shared by all users
+        #[cfg(unix)]
+        None => std::env::temp_dir().join(format!("frostbyte-{}", unsafe { libc::geteuid() })),
+        #[cfg(not(unix))]
+        None => std::env::temp_dir().join("frostbyte"),
+    };
+    create_private_dir(&dir)?;
+
+    Ok(dir)
+}
+
+/// Refuses directories another user could have created or may access.
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+        result => return result,
+    }
+
+    // not following symlinks, they could point anywhere
+    let metadata = std::fs::symlink_metadata(dir)?;
+    let owned = metadata.uid() == unsafe { libc::geteuid() };
+    if !metadata.is_dir() || !owned || metadata.mode() & 0o077 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{} isn't a private directory of this user", dir.display()),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)
+}
+
+fn parse_color(key: &str, value: Option<&str>) -> Option<iced::Color> {
+    let value = value?;
+    let color = iced::Color::parse(value);
+    if color.is_none() {
+        eprintln!("Invalid color for {}: {}", key, value);
+    }
+    color
+}
+
+#[cfg(windows)]
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(not(windows))]
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_link_regex_is_checked_on_load() {
+        let config: Config = toml::from_str(
+            r#"
+            [[smart_links]]
+            regex = '#(\d+)'
+            command = "xdg-open https://example.com/issues/$1"
+            "#,
+        )
+        .unwrap();
+        let command = config.smart_links[0].command_at("see #42", 5).unwrap();
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["https://example.com/issues/42"]
+        );
+        assert!(config.smart_links[0].command_at("see #42", 1).is_none());
+
+        let invalid = toml::from_str::<Config>(
+            r#"
+            [[smart_links]]
+            regex = '(unclosed'
+            command = "true"
+            "#,
+        );
+        assert!(invalid.is_err());
+    }
+}
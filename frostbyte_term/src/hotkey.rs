@@ -0,0 +1,120 @@
+use global_hotkey::hotkey;
+use iced::keyboard;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hotkey {
+    F12,
+    AltF12,
+    Pause,
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::F12 => write!(f, "F12"),
+            Self::AltF12 => write!(f, "Alt+F12"),
+            Self::Pause => write!(f, "Pause"),
+        }
+    }
+}
+
+impl Default for Hotkey {
+    fn default() -> Self {
+        if std::env::var_os("DEBUG").is_some() {
+            return Self::Pause;
+        }
+        #[cfg(target_os = "linux")]
+        return Self::F12;
+        #[cfg(not(target_os = "linux"))]
+        return Self::AltF12;
+    }
+}
+
+impl Hotkey {
+    pub fn global_hotkey(&self) -> hotkey::HotKey {
+        match self {
+            Self::F12 => hotkey::HotKey::new(None, hotkey::Code::F12),
+            Self::AltF12 => hotkey::HotKey::new(Some(hotkey::Modifiers::ALT), hotkey::Code::F12),
+            Self::Pause => hotkey::HotKey::new(None, hotkey::Code::Pause),
+        }
+    }
+
+    pub fn iced(&self) -> (iced::keyboard::Key, iced::keyboard::Modifiers) {
+        match self {
+            Self::F12 => (
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::F12),
+                iced::keyboard::Modifiers::empty(),
+            ),
+            Self::AltF12 => (
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::F12),
+                iced::keyboard::Modifiers::ALT,
+            ),
+            Self::Pause => (
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::Pause),
+                iced::keyboard::Modifiers::empty(),
+            ),
+        }
+    }
+
+    /// Keys which are used by frostbyte itself and shouldn't reach the terminal
+    pub fn filter(
+        hotkeys: Vec<Self>,
+    ) -> impl 'static + Fn(&iced::keyboard::Key, &iced::keyboard::Modifiers) -> bool {
+        let hotkeys: Vec<_> = hotkeys.iter().map(Self::iced).collect();
+        move |key: &iced::keyboard::Key, modifiers: &iced::keyboard::Modifiers| {
+            if modifiers.control() && modifiers.shift() {
+                match key {
+                    keyboard::Key::Named(named) => match named {
+                        keyboard::key::Named::ArrowLeft => return true,
+                        keyboard::key::Named::ArrowRight => return true,
+                        keyboard::key::Named::ArrowUp => return true,
+                        keyboard::key::Named::ArrowDown => return true,
+                        keyboard::key::Named::PageUp => return true,
+                        keyboard::key::Named::PageDown => return true,
+                        keyboard::key::Named::Space => return true,
+                        keyboard::key::Named::Home => return true,
+                        keyboard::key::Named::Delete => return true,
+                        _ => {}
+                    },
+                    // has to match the shortcuts in the subscription, case included,
+                    // or the key reaches the shell as well
+                    keyboard::Key::Character(character) => {
+                        match character.to_ascii_lowercase().as_str() {
+                            "t" => return true,
+                            "l" => return true,
+                            "p" => return true,
+                            "o" => return true,
+                            "b" => return true,
+                            "s" => return true,
+                            "r" => return true,
+                            "i" => return true,
+                            "y" => return true,
+                            "]" | "}" => return true,
+                            "[" | "{" => return true,
+                            "`" | "~" => return true,
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if modifiers.control() && modifiers.alt() {
+                match key {
+                    keyboard::Key::Named(keyboard::key::Named::ArrowUp) => return true,
+                    keyboard::Key::Named(keyboard::key::Named::ArrowDown) => return true,
+                    _ => {}
+                }
+            }
+            if hotkeys
+                .iter()
+                .any(|(hotkey, hotkey_modifiers)| key == hotkey && modifiers == hotkey_modifiers)
+            {
+                return true;
+            }
+
+            false
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Control of a running instance over a unix socket.
+//!
+//! The client sends its command line arguments separated by NUL bytes and closes its write half.
+//! The instance answers with `ok` or `error: <reason>` and closes the connection.
+
+use std::{
+    fmt::Debug,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use iced::keyboard::{Key, Modifiers, key::Named};
+use tokio::sync::oneshot;
+
+use crate::config;
+
+pub const USAGE: &str = "usage:
+  frostbyte_term send <tab> <text>   type text into a tab
+  frostbyte_term keys <tab> <key>... press keys in a tab, e.g. Enter, Tab, Up, Ctrl-C
+  frostbyte_term record <tab>        start or stop recording a tab into a .cast file
+  frostbyte_term focus-tab <text>    show the window with the first tab whose title contains text
+
+tabs are counted from 1 in tab bar order";
+
+/// Clients which don't finish sending their request in time are dropped
+#[cfg(unix)]
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// A request is a handful of arguments, anything larger is cut off
+#[cfg(unix)]
+const MAX_REQUEST_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Writes input into the tab at the given position, counted from 0
+    Input { tab: usize, input: Vec<u8> },
+    /// Presses keys in the tab at the given position, encoded according to the modes of the tab,
+    /// e.g. the cursor keys in application mode
+    Keys {
+        tab: usize,
+        keys: Vec<(Key, Modifiers)>,
+    },
+    /// Starts or stops recording the tab at the given position
+    ToggleRecording { tab: usize },
+    /// Shows the window with the first tab whose title contains the text
+    FocusTab { title: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub command: Command,
+    reply: Reply,
+}
+
+impl Request {
+    /// Answers the client. Only the first reply is sent.
+    pub fn reply(&self, result: Result<(), String>) {
+        if let Some(sender) = self.reply.0.lock().unwrap().take() {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Reply(Arc<Mutex<Option<oneshot::Sender<Result<(), String>>>>>);
+
+impl Debug for Reply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reply").finish()
+    }
+}
+
+pub fn socket_path() -> std::io::Result<PathBuf> {
+    Ok(config::runtime_dir()?.join("ipc.sock"))
+}
+
+pub fn parse_command(args: &[String]) -> Result<Command, String> {
+    match args {
+        [command, tab, text] if command == "send" => Ok(Command::Input {
+            tab: parse_tab(tab)?,
+            input: text.clone().into_bytes(),
+        }),
+        [command, tab, keys @ ..] if command == "keys" && !keys.is_empty() => Ok(Command::Keys {
+            tab: parse_tab(tab)?,
+            keys: keys
+                .iter()
+                .map(|key| parse_key(key))
+                .collect::<Result<_, _>>()?,
+        }),
+        [command, tab] if command == "record" => Ok(Command::ToggleRecording {
+            tab: parse_tab(tab)?,
+        }),
+        [command, title] if command == "focus-tab" => Ok(Command::FocusTab {
+            title: title.clone(),
+        }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn parse_tab(tab: &str) -> Result<usize, String> {
+    match tab.parse::<usize>() {
+        Ok(tab) if tab > 0 => Ok(tab - 1),
+        _ => Err(format!("invalid tab: {}", tab)),
+    }
+}
+
+fn parse_key(key: &str) -> Result<(Key, Modifiers), String> {
+    let named = match key.to_lowercase().as_str() {
+        "enter" | "return" => Named::Enter,
+        "tab" => Named::Tab,
+        "escape" | "esc" => Named::Escape,
+        "backspace" => Named::Backspace,
+        "space" => Named::Space,
+        "up" => Named::ArrowUp,
+        "down" => Named::ArrowDown,
+        "right" => Named::ArrowRight,
+        "left" => Named::ArrowLeft,
+        "home" => Named::Home,
+        "end" => Named::End,
+        "delete" => Named::Delete,
+        other => {
+            if let Some(letter) = other.strip_prefix("ctrl-")
+                && let [letter] = letter.as_bytes()
+                && letter.is_ascii_lowercase()
+            {
+                let letter = (*letter as char).to_string();
+                return Ok((Key::Character(letter.into()), Modifiers::CTRL));
+            }
+            return Err(format!("unknown key: {}", key));
+        }
+    };
+
+    Ok((Key::Named(named), Modifiers::empty()))
+}
+
+/// Sends the arguments to the running instance and returns the exit code.
+#[cfg(unix)]
+pub fn run_client(args: &[String]) -> i32 {
+    use std::{
+        io::{Read, Write},
+        net::Shutdown,
+        os::unix::net::UnixStream,
+    };
+
+    if let Err(err) = parse_command(args) {
+        eprintln!("{}", err);
+        return 2;
+    }
+
+    let result = (|| {
+        let mut stream = UnixStream::connect(socket_path()?)?;
+        stream.write_all(args.join("\0").as_bytes())?;
+        stream.shutdown(Shutdown::Write)?;
+
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply)?;
+        std::io::Result::Ok(reply)
+    })();
+
+    match result {
+        Ok(reply) if reply == "ok" => 0,
+        Ok(reply) => {
+            eprintln!("{}", reply);
+            1
+        }
+        Err(err) => {
+            eprintln!("Error connecting to frostbyte: {}", err);
+            1
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn run_client(_args: &[String]) -> i32 {
+    eprintln!("Controlling frostbyte from the command line is only supported on unix");
+    1
+}
+
+#[cfg(unix)]
+pub fn listen() -> impl iced::futures::Stream<Item = crate::ui::Message> {
+    use tokio::net::UnixListener;
+
+    iced::stream::channel(32, async |sender| {
+        let path = match socket_path() {
+            Ok(path) => path,
+            Err(err) => {
+                eprintln!("Error creating ipc directory: {}", err);
+                return;
+            }
+        };
+
+        // only the stale socket of a previous instance may be replaced
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            eprintln!(
+                "Another instance is listening on {}, not accepting commands",
+                path.display()
+            );
+            return;
+        }
+        let _ = std::fs::remove_file(&path);
+
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!("Error binding ipc socket {}: {}", path.display(), err);
+                return;
+            }
+        };
+
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+
+            // a client which never finishes its request mustn't hold up the others
+            tokio::spawn(serve(stream, sender.clone()));
+        }
+    })
+}
+
+#[cfg(unix)]
+async fn serve(
+    mut stream: tokio::net::UnixStream,
+    mut sender: iced::futures::channel::mpsc::Sender<crate::ui::Message>,
+) {
+    use iced::futures::SinkExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut request = Vec::new();
+    let read = (&mut stream)
+        .take(MAX_REQUEST_SIZE)
+        .read_to_end(&mut request);
+    if !matches!(tokio::time::timeout(REQUEST_TIMEOUT, read).await, Ok(Ok(_))) {
+        return;
+    }
+
+    let args: Vec<String> = String::from_utf8_lossy(&request)
+        .split('\0')
+        .map(str::to_string)
+        .collect();
+
+    let result = match parse_command(&args) {
+        Ok(command) => {
+            let (reply_send, reply_recv) = oneshot::channel();
+            let request = Request {
+                command,
+                reply: Reply(Arc::new(Mutex::new(Some(reply_send)))),
+            };
+
+            if let Err(err) = sender.send(crate::ui::Message::Ipc(request)).await {
+                eprintln!("Error sending ipc message: {}", err);
+            }
+
+            reply_recv
+                .await
+                .unwrap_or_else(|_| Err("request was dropped".to_string()))
+        }
+        Err(err) => Err(err),
+    };
+
+    let reply = match result {
+        Ok(()) => "ok".to_string(),
+        Err(err) => format!("error: {}", err),
+    };
+    let _ = stream.write_all(reply.as_bytes()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn keys_are_pressed_not_encoded() {
+        let Ok(Command::Keys { tab, keys }) = parse_command(&args(&["keys", "2", "Up", "ctrl-c"]))
+        else {
+            panic!("keys weren't parsed");
+        };
+        assert_eq!(tab, 1);
+        assert_eq!(
+            keys,
+            [
+                (Key::Named(Named::ArrowUp), Modifiers::empty()),
+                (Key::Character("c".into()), Modifiers::CTRL),
+            ]
+        );
+
+        assert!(parse_command(&args(&["keys", "1", "ctrl-shift-c"])).is_err());
+        assert!(parse_command(&args(&["keys", "0", "Up"])).is_err());
+    }
+}
@@ -1,6 +1,12 @@
 #![windows_subsystem = "windows"]
 
+mod args;
+mod config;
+mod hotkey;
+mod ipc;
+mod scrollback_log;
 mod ui;
+mod wallpaper;
 
 #[cfg(target_os = "linux")]
 use iced_layershell::settings::{LayerShellSettings, StartMode};
@@ -9,6 +15,11 @@ use ui::UI;
 const FONT: &[u8] = include_bytes!("../fonts/RobotoMonoNerdFont-Regular.ttf");
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = args::handle(&args) {
+        std::process::exit(code);
+    }
+
     #[cfg(target_os = "linux")]
     if std::env::var_os("WAYLAND_DISPLAY").is_some() && std::env::var_os("DEBUG").is_none() {
         run_layershell();
@@ -25,9 +36,13 @@ fn run_iced() {
         .subscription(UI::subscription)
         .title(UI::title)
         .theme(iced::Theme::Dark)
+        .style(UI::style)
         .antialiasing(true)
         .run()
-        .unwrap();
+        .unwrap_or_else(|err| {
+            eprintln!("Error running frostbyte: {}", err);
+            std::process::exit(1);
+        });
 }
 
 #[cfg(target_os = "linux")]
@@ -41,11 +56,15 @@ fn run_layershell() {
     .font(FONT)
     .subscription(UI::subscription)
     .theme(|_: &'_ UI, _| iced::Theme::Dark)
+    .style(UI::style)
     .antialiasing(true)
     .layer_settings(LayerShellSettings {
         start_mode: StartMode::Background,
         ..Default::default()
     })
     .run()
-    .unwrap();
+    .unwrap_or_else(|err| {
+        eprintln!("Error running frostbyte: {}", err);
+        std::process::exit(1);
+    });
 }
@@ -0,0 +1,45 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use crate::config;
+
+/// Logs which haven't been written to for this long get removed on startup.
+const RETENTION: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+pub fn log_dir() -> std::io::Result<PathBuf> {
+    Ok(config::runtime_dir()?.join("scrollback"))
+}
+
+/// Each process gets its own set of logs, so a crashed session isn't overwritten by the next one.
+pub fn log_path(tab_id: u32) -> std::io::Result<PathBuf> {
+    Ok(log_dir()?.join(format!("{}-tab-{}.log", std::process::id(), tab_id)))
+}
+
+/// Removes logs of old sessions to avoid unbounded disk usage.
+pub fn cleanup() {
+    let Ok(entries) = log_dir().and_then(std::fs::read_dir) else {
+        return;
+    };
+
+    let now = SystemTime::now();
+
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map(|modified| now.duration_since(modified).unwrap_or_default() > RETENTION)
+            .unwrap_or(false);
+
+        if expired {
+            if let Err(err) = std::fs::remove_file(entry.path()) {
+                eprintln!(
+                    "Error removing old scrollback log {}: {}",
+                    entry.path().display(),
+                    err
+                );
+            }
+        }
+    }
+}
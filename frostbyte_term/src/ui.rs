@@ -1,25 +1,25 @@
-use std::{collections::BTreeMap, fmt::Debug};
+use std::{collections::BTreeMap, fmt::Debug, time::Duration};
 
 #[cfg(target_os = "linux")]
 use std::sync::{
     Arc,
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 
 #[cfg(target_os = "linux")]
-use signal_hook::consts::signal::SIGUSR1;
+use signal_hook::consts::signal::{SIGINT, SIGTERM, SIGUSR1};
 #[cfg(target_os = "linux")]
 use signal_hook::flag as signal_flag;
 
-use frozen_term::local_terminal::{self, LocalTerminal};
-use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState, hotkey};
+use frozen_term::local_terminal::{self, Command, InputSequence, LocalTerminal, OutputLog};
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
 use iced::{
     Alignment::Center,
     Element, Font, Length, Point, Subscription, Task,
     futures::{SinkExt, Stream},
-    keyboard,
+    keyboard, mouse,
     stream::channel,
-    widget::{button, center, column, container, row, text},
+    widget::{button, center, column, container, mouse_area, opaque, row, stack, text, tooltip},
     window::{self, MonitorIndex, PositionOnMonitor},
 };
 #[cfg(target_os = "linux")]
@@ -27,6 +27,16 @@ use iced_layershell::reexport::{Anchor, NewLayerShellSettings};
 use image::GenericImageView;
 use tray_icon::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder};
 
+use crate::{
+    config::{
+        BellAction, Config, HotkeyAction, LastTabClose, NamedPosition, TabCloseButton, TabLabel,
+        ThemeColors, TitleSource, UrlPasteAction, WindowCloseButton, WindowPosition,
+    },
+    hotkey::Hotkey,
+    ipc, scrollback_log,
+    wallpaper::Wallpaper,
+};
+
 /// Messages emitted by the application and its widgets.
 #[cfg_attr(target_os = "linux", iced_layershell::to_layer_message(multi))]
 #[derive(Debug, Clone)]
@@ -36,12 +46,51 @@ pub enum Message {
         message: local_terminal::Message,
     },
     OpenTab,
+    OpenTabWithProfile(String),
+    OpenTmux,
+    HoverTab(Option<u32>),
+    /// Moves the tab into the next broadcast group
+    CycleTabGroup(u32),
+    ToggleProfileMenu,
     SwitchTab(u32),
     CloseTab(u32),
+    CloseAllTabs,
+    ToggleLock,
+    ToggleFreeze,
+    /// Shows the control characters in the output of the selected tab instead of interpreting them
+    ToggleControlChars,
+    /// Starts or stops recording the selected tab into a `.cast` file
+    ToggleRecording,
+    LocateCursor,
+    SelectLastOutput,
+    CycleTheme,
+    ScrollToLastPrompt,
+    ResetTerminal,
+    /// Opens the window on the given tab, e.g. when it's picked in the tray menu
+    ShowTab(u32),
+    TogglePin,
+    ToggleTabBar,
     Hotkey,
+    /// A registered global hotkey was pressed
+    GlobalHotkey(u32),
+    /// A keyboard shortcut, which is passed to the program instead while it isn't the shell
+    Shortcut {
+        message: Box<Message>,
+        key_press: frozen_term::KeyPress,
+    },
     WindowOpened(window::Id),
     CloseWindow,
+    /// The window manager asked to close the window, e.g. via Alt+F4
+    WindowCloseRequested,
     Shutdown,
+    /// Quits without confirmation, e.g. when receiving SIGTERM
+    Terminate,
+    /// Answers the confirmation dialog with yes
+    Confirm,
+    CancelConfirmation,
+    OpenPastedUrl,
+    PastePastedUrl,
+    CancelUrlPaste,
     // This does nothing as is only here to trigger a redraw
     Redraw,
     NextMonitor,
@@ -49,6 +98,31 @@ pub enum Message {
     UpdateMonitor(MonitorIndex),
     PreviousTab,
     NextTab,
+    /// Switches back to the previously selected tab
+    LastUsedTab,
+    MoveTabLeft,
+    MoveTabRight,
+    IncreaseHeight,
+    DecreaseHeight,
+    OpacityUp,
+    OpacityDown,
+    UpdateHeightOffset(f32),
+    Ipc(ipc::Request),
+    WindowFocused(bool),
+    /// Hides the window unless its focus changed again since the given focus change
+    HideAfterFocusLoss(u32),
+    /// Hides the tab limit notice unless another tab was refused since
+    HideTabLimitNotice(u32),
+    WindowResized(iced::Size),
+    /// The mouse wheel was turned over the tab bar
+    ScrollTabBar(mouse::ScrollDelta),
+}
+
+/// What the confirmation dialog asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirmation {
+    Quit,
+    CloseAllTabs,
 }
 
 enum Mode {
@@ -59,17 +133,96 @@ enum Mode {
 
 const ICON: &'static [u8] = include_bytes!("../assets/icon.png");
 
+const NERD_FONT: Font = Font::with_name("RobotoMono Nerd Font");
+const LOCK_ICON: &str = "\u{f023}";
+const PIN_ICON: &str = "\u{f08d}";
+const FROZEN_ICON: &str = "\u{f04c}";
+const RECORDING_ICON: &str = "\u{f111}";
+
+const TRAY_CLOSE_ALL_TABS: &str = "close_all_tabs";
+const TRAY_TMUX: &str = "tmux";
+const TRAY_EXIT: &str = "exit";
+const TRAY_TOOLTIP: &str = "Frostbyte";
+/// Menu item ids of the tabs are this prefix followed by the tab id
+const TRAY_TAB_PREFIX: &str = "tab:";
+/// How often the gtk thread picks up tray changes
+#[cfg(target_os = "linux")]
+const TRAY_POLL: Duration = Duration::from_millis(250);
+
+const WINIT_HEIGHT_FACTOR: f32 = 0.45;
+#[cfg(target_os = "linux")]
+const LAYERSHELL_HEIGHT: f32 = 600.0;
+const MIN_HEIGHT: f32 = 100.0;
+const OPACITY_STEP: f32 = 0.1;
+const MIN_OPACITY: f32 = 0.1;
+const MAX_INITIAL_TABS: usize = 10;
+/// How long the notice about the tab limit is shown
+const TAB_LIMIT_NOTICE_DURATION: Duration = Duration::from_secs(3);
+
+/// Colors of the stripes marking the broadcast groups of the tabs
+const GROUP_COLORS: [iced::Color; 4] = [
+    iced::Color::from_rgb(0.9, 0.3, 0.3),
+    iced::Color::from_rgb(0.3, 0.8, 0.4),
+    iced::Color::from_rgb(0.3, 0.5, 0.9),
+    iced::Color::from_rgb(0.9, 0.7, 0.2),
+];
+
 pub struct UI {
     terminals: BTreeMap<u32, LocalTerminal>,
+    /// Order of the tabs in the tab bar
+    tab_order: Vec<u32>,
     window_id: Option<window::Id>,
     selected_tab: u32,
+    /// The tab selected before the current one
+    previous_tab: Option<u32>,
     new_terminal_id: u32,
-    _hotkey_manager: GlobalHotKeyManager,
-    hotkey: Hotkey,
-    hotkey_id: u32,
-    _tray_icon: Option<TrayIcon>,
+    _hotkey_manager: Option<GlobalHotKeyManager>,
+    /// The registered global hotkeys by their id
+    hotkeys: BTreeMap<u32, (Hotkey, HotkeyAction)>,
+    tray: Option<Tray>,
+    /// On Linux the tray icon lives on the gtk thread, changes are sent to it
+    #[cfg(target_os = "linux")]
+    tray_sender: Option<std::sync::mpsc::Sender<TrayState>>,
+    /// The state currently shown by the tray icon
+    tray_state: TrayState,
+    /// A tab changed its title or wrote its first unseen output
+    tray_outdated: bool,
     mode: Mode,
     monitor: MonitorIndex,
+    /// Session-wide height adjustment on top of the default window height
+    height_offset: f32,
+    /// Set by the opacity shortcuts for the rest of the session, replaces the configured opacity.
+    /// A configured `unfocused_opacity` still applies while the window isn't focused.
+    opacity_override: Option<f32>,
+    /// Whether the current window was created transparent
+    window_transparent: bool,
+    /// Set while the confirmation dialog is shown
+    confirming: Option<Confirmation>,
+    /// A URL pasted into a tab, waiting for the choice between opening and pasting it
+    pasted_url: Option<(u32, String)>,
+    /// Set while the notice about the tab limit is shown, counts the refused tabs
+    tab_limit_notice: Option<u32>,
+    profile_menu_open: bool,
+    /// A pinned window isn't hidden by the hotkey
+    pinned: bool,
+    /// Hidden by Ctrl+Shift+B for the rest of the session
+    show_tab_bar: bool,
+    /// The current theme, switched by Ctrl+Shift+Y for the rest of the session
+    theme: Option<String>,
+    theme_colors: Option<ThemeColors>,
+    /// The tab below the mouse cursor
+    hovered_tab: Option<u32>,
+    /// Broadcast group of each tab as an index into `config.tab_groups`
+    tab_groups: BTreeMap<u32, usize>,
+    wallpaper: Option<Wallpaper>,
+    /// The app icon, shown while there are no tabs
+    logo: iced::widget::image::Handle,
+    window_focused: bool,
+    /// Counts focus changes, to cancel hiding once the focus returned
+    focus_changes: u32,
+    /// Unknown until the window reports its size
+    window_width: Option<f32>,
+    config: Config,
 }
 
 impl Debug for UI {
@@ -78,78 +231,309 @@ impl Debug for UI {
             .field("window_id", &self.window_id)
             .field("selected_tab", &self.selected_tab)
             .field("new_terminal_id", &self.new_terminal_id)
-            .field("hotkey_id", &self.hotkey_id)
+            .field("hotkeys", &self.hotkeys.keys())
             .finish()
     }
 }
 
-impl UI {
-    fn create_tray_icon() -> TrayIcon {
-        let close_item = tray_icon::menu::MenuItem::new("Exit Frostbyte", true, None);
+/// What the tray icon shows about the tabs
+#[derive(Debug, Clone, PartialEq)]
+struct TrayState {
+    tooltip: String,
+    /// Ids and titles of the tabs in tab bar order
+    tabs: Vec<(u32, String)>,
+}
+
+/// The tray icon along with its menu of tabs
+struct Tray {
+    icon: TrayIcon,
+    tabs_menu: tray_icon::menu::Submenu,
+    tab_items: Vec<tray_icon::menu::MenuItem>,
+    state: TrayState,
+}
+
+impl Tray {
+    fn create() -> Result<Self, Box<dyn std::error::Error>> {
+        let tabs_menu = tray_icon::menu::Submenu::new("Tabs", false);
+        let close_tabs_item =
+            tray_icon::menu::MenuItem::with_id(TRAY_CLOSE_ALL_TABS, "Close All Tabs", true, None);
+        let close_item =
+            tray_icon::menu::MenuItem::with_id(TRAY_EXIT, "Exit Frostbyte", true, None);
+        let tmux_item =
+            tray_icon::menu::MenuItem::with_id(TRAY_TMUX, "Attach tmux Session", true, None);
         let tray_menu = tray_icon::menu::Menu::new();
-        tray_menu.append(&close_item).unwrap();
+        tray_menu.append(&tabs_menu)?;
+        tray_menu.append(&tmux_item)?;
+        tray_menu.append(&close_tabs_item)?;
+        tray_menu.append(&close_item)?;
 
-        let icon = image::load_from_memory_with_format(ICON, image::ImageFormat::Png).unwrap();
+        let icon = image::load_from_memory_with_format(ICON, image::ImageFormat::Png)?;
         let (width, height) = icon.dimensions();
         let icon_data = icon.into_rgba8().to_vec();
 
-        TrayIconBuilder::new()
-            .with_tooltip("Frostbyte")
+        let tray_icon = TrayIconBuilder::new()
+            .with_tooltip(TRAY_TOOLTIP)
             .with_menu(Box::new(tray_menu))
             .with_menu_on_left_click(false)
-            .with_icon(tray_icon::Icon::from_rgba(icon_data, width, height).unwrap())
-            .build()
-            .unwrap()
+            .with_icon(tray_icon::Icon::from_rgba(icon_data, width, height)?)
+            .build()?;
+
+        Ok(Self {
+            icon: tray_icon,
+            tabs_menu,
+            tab_items: Vec::new(),
+            state: TrayState {
+                tooltip: TRAY_TOOLTIP.to_string(),
+                tabs: Vec::new(),
+            },
+        })
+    }
+
+    fn apply(&mut self, state: TrayState) {
+        if state.tooltip != self.state.tooltip
+            && let Err(err) = self.icon.set_tooltip(Some(&state.tooltip))
+        {
+            eprintln!("Error updating tray tooltip: {}", err);
+        }
+
+        if state.tabs != self.state.tabs {
+            for item in self.tab_items.drain(..) {
+                if let Err(err) = self.tabs_menu.remove(&item) {
+                    eprintln!("Error removing tab from the tray menu: {}", err);
+                }
+            }
+            for (id, title) in &state.tabs {
+                let item = tray_icon::menu::MenuItem::with_id(
+                    format!("{}{}", TRAY_TAB_PREFIX, id),
+                    title,
+                    true,
+                    None,
+                );
+                if let Err(err) = self.tabs_menu.append(&item) {
+                    eprintln!("Error adding tab to the tray menu: {}", err);
+                }
+                self.tab_items.push(item);
+            }
+            self.tabs_menu.set_enabled(!state.tabs.is_empty());
+        }
+
+        self.state = state;
+    }
+}
+
+impl UI {
+    /// Registers the global hotkey, trying the fallback if the key is already taken by
+    /// another application. Returns the hotkey that got registered.
+    ///
+    /// Without a hotkey the window can still be toggled using the tray icon or SIGUSR1.
+    fn register_hotkey(
+        hotkey_manager: &GlobalHotKeyManager,
+        hotkey: Hotkey,
+        fallback: Option<Hotkey>,
+    ) -> Option<Hotkey> {
+        let candidates = std::iter::once(hotkey).chain(fallback.filter(|f| *f != hotkey));
+        for candidate in candidates {
+            match hotkey_manager.register(candidate.global_hotkey()) {
+                Ok(()) => return Some(candidate),
+                Err(global_hotkey::Error::AlreadyRegistered(_)) => {
+                    eprintln!(
+                        "Warning: the global hotkey {} is already in use by another application",
+                        candidate
+                    );
+                }
+                Err(err) => {
+                    eprintln!("Error registering global hotkey {}: {}", candidate, err);
+                }
+            }
+        }
+        eprintln!("Running without a global hotkey, use the tray icon or SIGUSR1 to toggle");
+        None
     }
 
     pub fn start_winit() -> (Self, Task<Message>) {
-        Self::start_in_mode(Mode::Winit)
+        Self::start_in_mode(Mode::Winit, true, Config::load())
     }
 
     #[cfg(target_os = "linux")]
     pub fn start_layershell() -> (Self, Task<Message>) {
-        Self::start_in_mode(Mode::Layershell)
+        Self::start_in_mode(Mode::Layershell, true, Config::load())
+    }
+
+    /// Starts with the default config and without a tray icon and global hotkey,
+    /// as those require a desktop session, e.g. to run the tests.
+    #[cfg(test)]
+    fn start_headless() -> (Self, Task<Message>) {
+        Self::start_in_mode(Mode::Winit, false, Config::default())
     }
 
-    fn start_in_mode(mode: Mode) -> (Self, Task<Message>) {
+    fn start_in_mode(mode: Mode, integrate_desktop: bool, config: Config) -> (Self, Task<Message>) {
+        // the tray receivers in `poll_events_sub` simply stay empty without a tray icon
+        let show_tray = integrate_desktop && config.tray;
+
         #[cfg(target_os = "linux")]
-        std::thread::spawn(|| {
-            gtk::init().unwrap();
-            let _tray_icon = Self::create_tray_icon();
+        let tray_sender = if show_tray {
+            let (sender, receiver) = std::sync::mpsc::channel::<TrayState>();
+            std::thread::spawn(move || {
+                if let Err(err) = gtk::init() {
+                    eprintln!("Error initializing gtk, running without tray icon: {}", err);
+                    return;
+                }
+                let mut tray = match Tray::create() {
+                    Ok(tray) => tray,
+                    Err(err) => {
+                        eprintln!("Error creating tray icon: {}", err);
+                        return;
+                    }
+                };
+
+                // keeps the tray icon alive for as long as gtk runs
+                gtk::glib::timeout_add_local(TRAY_POLL, move || {
+                    while let Ok(state) = receiver.try_recv() {
+                        tray.apply(state);
+                    }
+                    gtk::glib::ControlFlow::Continue
+                });
 
-            gtk::main();
-        });
+                gtk::main();
+            });
+            Some(sender)
+        } else {
+            None
+        };
         #[cfg(target_os = "linux")]
-        let tray_icon = None;
+        let tray = None;
         #[cfg(not(target_os = "linux"))]
-        let tray_icon = Some(Self::create_tray_icon());
+        let tray = if show_tray {
+            Tray::create()
+                .inspect_err(|err| eprintln!("Error creating tray icon: {}", err))
+                .ok()
+        } else {
+            None
+        };
 
         let terminals = BTreeMap::new();
 
-        let hotkey = Hotkey::default();
-        let global_hotkey = hotkey.global_hotkey();
-        let hotkey_id = global_hotkey.id;
-        let hotkey_manager = GlobalHotKeyManager::new().unwrap();
-        hotkey_manager.register(global_hotkey).unwrap();
-
-        (
-            Self {
-                terminals,
-                window_id: None,
-                selected_tab: 1,
-                new_terminal_id: 1,
-                _hotkey_manager: hotkey_manager,
-                hotkey_id,
-                hotkey,
-                _tray_icon: tray_icon,
-                mode,
-                monitor: MonitorIndex(0),
+        let wallpaper = Wallpaper::load(&config);
+        if config.scrollback_log
+            || config
+                .profiles
+                .iter()
+                .any(|profile| profile.scrollback_log == Some(true))
+        {
+            scrollback_log::cleanup();
+        }
+
+        let mut hotkeys = BTreeMap::new();
+        let hotkey_manager = if integrate_desktop {
+            match GlobalHotKeyManager::new() {
+                Ok(hotkey_manager) => {
+                    if let Some(registered) = Self::register_hotkey(
+                        &hotkey_manager,
+                        Hotkey::default(),
+                        config.fallback_hotkey,
+                    ) {
+                        hotkeys.insert(
+                            registered.global_hotkey().id,
+                            (registered, HotkeyAction::Toggle),
+                        );
+                    }
+                    for binding in &config.hotkeys {
+                        match hotkey_manager.register(binding.key.global_hotkey()) {
+                            Ok(()) => {
+                                hotkeys.insert(
+                                    binding.key.global_hotkey().id,
+                                    (binding.key, binding.action.clone()),
+                                );
+                            }
+                            Err(err) => {
+                                eprintln!(
+                                    "Error registering global hotkey {}: {}",
+                                    binding.key, err
+                                );
+                            }
+                        }
+                    }
+                    Some(hotkey_manager)
+                }
+                Err(err) => {
+                    eprintln!("Error creating global hotkey manager: {}", err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut ui = Self {
+            terminals,
+            tab_order: Vec::new(),
+            window_id: None,
+            selected_tab: 1,
+            previous_tab: None,
+            new_terminal_id: 1,
+            _hotkey_manager: hotkey_manager,
+            hotkeys,
+            tray,
+            #[cfg(target_os = "linux")]
+            tray_sender,
+            tray_outdated: false,
+            tray_state: TrayState {
+                tooltip: TRAY_TOOLTIP.to_string(),
+                tabs: Vec::new(),
             },
-            Task::none(),
-        )
+            mode,
+            monitor: MonitorIndex(0),
+            height_offset: 0.0,
+            opacity_override: None,
+            window_transparent: false,
+            confirming: None,
+            pasted_url: None,
+            tab_limit_notice: None,
+            profile_menu_open: false,
+            pinned: false,
+            hovered_tab: None,
+            tab_groups: BTreeMap::new(),
+            wallpaper,
+            logo: iced::widget::image::Handle::from_bytes(ICON),
+            window_focused: true,
+            focus_changes: 0,
+            window_width: None,
+            show_tab_bar: true,
+            theme: config.theme.clone(),
+            theme_colors: config
+                .theme
+                .as_deref()
+                .and_then(|theme| config.theme_colors(theme)),
+            config,
+        };
+        // without tabs or a window until the tray or hotkey is used for the first time
+        let task = if ui.config.start_hidden {
+            Task::none()
+        } else {
+            ui.open_window()
+        };
+
+        (ui, task)
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
+        let shown_tabs = (
+            self.tab_order.clone(),
+            self.selected_tab,
+            self.window_id.is_some(),
+        );
+        let task = self.handle_message(message);
+        let tabs_changed = shown_tabs.0 != self.tab_order
+            || shown_tabs.1 != self.selected_tab
+            || shown_tabs.2 != self.window_id.is_some();
+        if tabs_changed || self.tray_outdated {
+            self.tray_outdated = false;
+            self.update_tray();
+        }
+        task
+    }
+
+    fn handle_message(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::LocalTerminal { id, message } => {
                 let term = match self.terminals.get_mut(&id) {
@@ -157,18 +541,110 @@ impl UI {
                     Some(term) => term,
                 };
 
+                let title = term.get_title().to_string();
+                let activity = term.has_activity();
                 let action = term.update(message);
 
+                // the shell reports directory changes along with its output
+                update_cwd_title(&self.config.title_source, term);
+                self.tray_outdated |= term.get_title() != title || term.has_activity() != activity;
+
                 match action {
                     local_terminal::Action::Close => self.close_tab(id),
                     local_terminal::Action::Run(task) => {
                         task.map(move |message| Message::LocalTerminal { id, message })
                     }
                     local_terminal::Action::IdChanged => self.focus_tab(),
+                    local_terminal::Action::KeyPressed(key_press) => {
+                        self.broadcast_key(id, key_press)
+                    }
+                    local_terminal::Action::CtrlClick { line, column } => {
+                        self.open_smart_link(&line, column);
+                        Task::none()
+                    }
+                    local_terminal::Action::UrlPasted(url) => match self.config.url_paste_action {
+                        UrlPasteAction::Paste => term
+                            .paste(&url)
+                            .map(move |message| Message::LocalTerminal { id, message }),
+                        UrlPasteAction::Ask => {
+                            self.pasted_url = Some((id, url));
+                            Task::none()
+                        }
+                        UrlPasteAction::Open => {
+                            open_url(&url);
+                            Task::none()
+                        }
+                    },
+                    local_terminal::Action::Bell => match (self.config.bell, self.window_id) {
+                        (BellAction::Urgency, Some(window_id))
+                            if !self.window_focused && matches!(self.mode, Mode::Winit) =>
+                        {
+                            window::request_user_attention(
+                                window_id,
+                                Some(window::UserAttention::Informational),
+                            )
+                        }
+                        _ => Task::none(),
+                    },
                     local_terminal::Action::None => Task::none(),
                 }
             }
-            Message::OpenTab => self.open_tab(),
+            Message::OpenTab => {
+                self.profile_menu_open = false;
+                self.open_tab()
+            }
+            Message::OpenTabWithProfile(name) => {
+                self.profile_menu_open = false;
+                match self
+                    .config
+                    .profiles
+                    .iter()
+                    .find(|profile| profile.name == name)
+                {
+                    Some(profile) => self.open_tab_with(
+                        profile.command(),
+                        profile.scrollback_log.unwrap_or(self.config.scrollback_log),
+                    ),
+                    None => Task::none(),
+                }
+            }
+            Message::OpenTmux => {
+                let mut parts = self
+                    .config
+                    .tmux_command
+                    .split_whitespace()
+                    .map(String::from);
+                let command = Command {
+                    program: parts.next(),
+                    args: parts.collect(),
+                    login: false,
+                };
+                Task::batch([
+                    self.open_tab_with(command, self.config.scrollback_log),
+                    self.open_window(),
+                ])
+            }
+            Message::CycleTabGroup(id) => {
+                let next = match self.tab_groups.get(&id) {
+                    None => Some(0),
+                    Some(group) => Some(group + 1),
+                }
+                .filter(|group| *group < self.config.tab_groups.len());
+
+                match next {
+                    Some(group) => self.tab_groups.insert(id, group),
+                    None => self.tab_groups.remove(&id),
+                };
+                Task::none()
+            }
+            Message::HoverTab(id) => {
+                self.hovered_tab = id;
+                Task::none()
+            }
+            Message::ToggleProfileMenu => {
+                self.profile_menu_open = !self.profile_menu_open;
+                Task::none()
+            }
             Message::SwitchTab(id) => {
                 // refocus tab if clicking on the already selected one
                 if self.selected_tab == id
@@ -181,39 +657,246 @@ impl UI {
                 }
             }
             Message::NextTab => {
-                let selected = self.selected_tab;
-                let next = self.terminals.keys().find(|key| **key > selected);
-                if let Some(next) = next {
+                if let Some(position) = self.selected_position()
+                    && let Some(next) = self.tab_order.get(position + 1)
+                {
                     self.switch_tab(*next);
                 }
                 Task::none()
             }
             Message::PreviousTab => {
-                let selected = self.selected_tab;
-                let previous = self.terminals.keys().rev().find(|key| **key < selected);
-                if let Some(next) = previous {
-                    self.switch_tab(*next);
+                if let Some(position) = self.selected_position()
+                    && let Some(previous) = position
+                        .checked_sub(1)
+                        .and_then(|position| self.tab_order.get(position))
+                {
+                    self.switch_tab(*previous);
+                }
+                Task::none()
+            }
+            Message::LastUsedTab => {
+                let previous = self
+                    .previous_tab
+                    .filter(|id| self.terminals.contains_key(id))
+                    .or_else(|| self.tab_order.first().copied());
+                if let Some(previous) = previous {
+                    self.switch_tab(previous);
+                }
+                Task::none()
+            }
+            Message::MoveTabLeft => {
+                if let Some(position) = self.selected_position()
+                    && position > 0
+                {
+                    self.tab_order.swap(position, position - 1);
+                }
+                Task::none()
+            }
+            Message::MoveTabRight => {
+                if let Some(position) = self.selected_position()
+                    && position + 1 < self.tab_order.len()
+                {
+                    self.tab_order.swap(position, position + 1);
                 }
                 Task::none()
             }
             Message::CloseTab(id) => self.close_tab(id),
+            Message::CloseAllTabs => self.close_all_tabs(),
+            Message::ShowTab(id) => {
+                if !self.terminals.contains_key(&id) {
+                    return Task::none();
+                }
+                self.switch_tab(id);
+                Task::batch([self.open_window(), self.focus_tab()])
+            }
+            Message::ToggleLock => {
+                if let Some(term) = self.terminals.get_mut(&self.selected_tab) {
+                    term.set_locked(!term.is_locked());
+                }
+                Task::none()
+            }
+            Message::ToggleFreeze => {
+                if let Some(term) = self.terminals.get_mut(&self.selected_tab) {
+                    term.set_frozen(!term.is_frozen());
+                }
+                Task::none()
+            }
+            Message::ToggleControlChars => {
+                let id = self.selected_tab;
+                match self.terminals.get_mut(&id) {
+                    Some(term) => term
+                        .set_show_control_chars(!term.shows_control_chars())
+                        .map(move |message| Message::LocalTerminal { id, message }),
+                    None => Task::none(),
+                }
+            }
+            Message::ToggleRecording => {
+                if let Err(err) = self.toggle_recording(self.selected_tab) {
+                    eprintln!("{}", err);
+                }
+                Task::none()
+            }
+            Message::GlobalHotkey(id) => match self.hotkeys.get(&id) {
+                Some((_, HotkeyAction::Toggle)) => Task::done(Message::Hotkey),
+                Some((_, HotkeyAction::ToggleOnMonitor { monitor })) => {
+                    if self.window_id.is_some() && self.monitor.0 == *monitor {
+                        Task::done(Message::Hotkey)
+                    } else {
+                        Task::done(Message::UpdateMonitor(MonitorIndex(*monitor)))
+                    }
+                }
+                Some((_, HotkeyAction::OpenProfile { profile })) => {
+                    match self.config.profiles.iter().find(|p| &p.name == profile) {
+                        Some(profile) => {
+                            let command = profile.command();
+                            let scrollback_log =
+                                profile.scrollback_log.unwrap_or(self.config.scrollback_log);
+                            Task::batch([
+                                self.open_tab_with(command, scrollback_log),
+                                self.open_window(),
+                            ])
+                        }
+                        None => {
+                            eprintln!("Unknown profile {} bound to a global hotkey", profile);
+                            Task::none()
+                        }
+                    }
+                }
+                None => Task::none(),
+            },
+            Message::SelectLastOutput => {
+                if let Some(term) = self.terminals.get_mut(&self.selected_tab) {
+                    term.select_last_output();
+                }
+                Task::none()
+            }
+            Message::CycleTheme => {
+                let themes = &self.config.theme_cycle;
+                if themes.is_empty() {
+                    return Task::none();
+                }
+                let next = self
+                    .theme
+                    .as_ref()
+                    .and_then(|current| themes.iter().position(|theme| theme == current))
+                    .map_or(0, |index| (index + 1) % themes.len());
+                let theme = themes[next].clone();
+                self.theme_colors = self.config.theme_colors(&theme);
+                self.theme = Some(theme);
+
+                let style = self.terminal_style();
+                for term in self.terminals.values_mut() {
+                    term.set_style(style.clone());
+                }
+                Task::none()
+            }
+            Message::ScrollToLastPrompt => {
+                if let Some(term) = self.terminals.get_mut(&self.selected_tab) {
+                    term.scroll_to_last_prompt();
+                }
+                Task::none()
+            }
+            Message::Shortcut { message, key_press } => {
+                let id = self.selected_tab;
+                if self.config.pass_shortcuts_to_programs
+                    && let Some(term) = self.terminals.get_mut(&id)
+                    && term.runs_foreground_job()
+                {
+                    // the terminal itself ignored the key, as the filter takes all shortcuts
+                    return term
+                        .press_key(key_press)
+                        .map(move |message| Message::LocalTerminal { id, message });
+                }
+                self.handle_message(*message)
+            }
+            Message::ResetTerminal => {
+                if let Some(term) = self.terminals.get_mut(&self.selected_tab) {
+                    term.reset();
+                }
+                Task::none()
+            }
+            Message::ToggleTabBar => {
+                self.show_tab_bar = !self.show_tab_bar;
+                self.profile_menu_open = false;
+                Task::none()
+            }
+            Message::LocateCursor => {
+                if let Some(term) = self.terminals.get_mut(&self.selected_tab) {
+                    term.locate_cursor();
+                }
+                Task::none()
+            }
             Message::Hotkey => {
-                if self.window_id.is_some() {
-                    self.close_window()
+                if let Some(id) = self.window_id {
+                    if self.pinned {
+                        window::gain_focus(id)
+                    } else {
+                        self.close_window()
+                    }
                 } else {
                     self.open_window()
                 }
             }
             Message::WindowOpened(id) => {
                 self.window_id = Some(id);
-                if let Some(term) = self.terminals.get(&self.selected_tab) {
+                self.validate_selected_tab();
+                if let Some(term) = self.terminals.get_mut(&self.selected_tab) {
+                    term.clear_activity();
                     Task::batch([window::gain_focus(id), term.focus()])
                 } else {
                     Task::none()
                 }
             }
             Message::CloseWindow => self.close_window(),
-            Message::Shutdown => iced::exit(),
+            Message::WindowCloseRequested => Task::done(self.close_button_message()),
+            Message::Shutdown => {
+                if self.config.confirm_quit
+                    && (self.terminals.len() > 1 || !self.busy_tabs().is_empty())
+                {
+                    self.confirming = Some(Confirmation::Quit);
+                    self.open_window()
+                } else {
+                    iced::exit()
+                }
+            }
+            Message::Confirm => match self.confirming.take() {
+                Some(Confirmation::Quit) => iced::exit(),
+                Some(Confirmation::CloseAllTabs) => self.clear_tabs(),
+                None => Task::none(),
+            },
+            Message::Terminate => {
+                // dropping the terminals hangs up the shells via SIGHUP
+                self.terminals.clear();
+                self.tab_order.clear();
+                self.tray = None;
+                iced::exit()
+            }
+            Message::CancelConfirmation => {
+                self.confirming = None;
+                self.focus_tab()
+            }
+            Message::OpenPastedUrl => {
+                if let Some((_, url)) = self.pasted_url.take() {
+                    open_url(&url);
+                }
+                self.focus_tab()
+            }
+            Message::PastePastedUrl => {
+                let Some((id, url)) = self.pasted_url.take() else {
+                    return Task::none();
+                };
+                let paste = match self.terminals.get_mut(&id) {
+                    Some(term) => term
+                        .paste(&url)
+                        .map(move |message| Message::LocalTerminal { id, message }),
+                    None => Task::none(),
+                };
+                Task::batch([paste, self.focus_tab()])
+            }
+            Message::CancelUrlPaste => {
+                self.pasted_url = None;
+                self.focus_tab()
+            }
             // only here to trigger a redraw
             Message::Redraw => Task::none(),
             Message::NextMonitor => {
@@ -239,6 +922,83 @@ impl UI {
                 self.monitor = index;
                 Task::batch([self.close_window(), self.open_window()])
             }
+            Message::IncreaseHeight => self.change_height(self.config.height_step),
+            Message::DecreaseHeight => self.change_height(-self.config.height_step),
+            Message::UpdateHeightOffset(offset) => {
+                self.height_offset = offset;
+                self.apply_height()
+            }
+            Message::OpacityUp => {
+                self.change_opacity(OPACITY_STEP);
+                Task::none()
+            }
+            Message::OpacityDown => {
+                self.change_opacity(-OPACITY_STEP);
+                Task::none()
+            }
+            Message::TogglePin => {
+                self.pinned = !self.pinned;
+                self.focus_tab()
+            }
+            Message::WindowFocused(focused) => {
+                self.window_focused = focused;
+                self.focus_changes = self.focus_changes.wrapping_add(1);
+                let style = self.terminal_style();
+                for term in self.terminals.values_mut() {
+                    term.set_style(style.clone());
+                }
+
+                if !focused && self.config.hide_on_focus_loss {
+                    // brief focus changes, e.g. from a quick Alt+Tab, don't hide the window
+                    let delay = Duration::from_millis(self.config.focus_loss_hide_delay_ms);
+                    let focus_change = self.focus_changes;
+                    Task::future(async move {
+                        tokio::time::sleep(delay).await;
+                        Message::HideAfterFocusLoss(focus_change)
+                    })
+                } else {
+                    Task::none()
+                }
+            }
+            Message::HideAfterFocusLoss(focus_change) => {
+                let dialog_open = self.confirming.is_some() || self.pasted_url.is_some();
+                if focus_change == self.focus_changes
+                    && !self.window_focused
+                    && !self.pinned
+                    && !dialog_open
+                {
+                    self.close_window()
+                } else {
+                    Task::none()
+                }
+            }
+            Message::HideTabLimitNotice(notice) => {
+                if self.tab_limit_notice == Some(notice) {
+                    self.tab_limit_notice = None;
+                }
+                Task::none()
+            }
+            Message::WindowResized(size) => {
+                self.window_width = Some(size.width);
+                Task::none()
+            }
+            Message::ScrollTabBar(delta) => {
+                let y = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } | mouse::ScrollDelta::Pixels { y, .. } => y,
+                };
+                if y > 0.0 {
+                    self.update(Message::PreviousTab)
+                } else if y < 0.0 {
+                    self.update(Message::NextTab)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::Ipc(request) => {
+                let (result, task) = self.handle_ipc(request.command.clone());
+                request.reply(result);
+                task
+            }
             #[cfg(target_os = "linux")]
             Message::AnchorChange { .. } => unreachable!(),
             #[cfg(target_os = "linux")]
@@ -272,6 +1032,150 @@ impl UI {
         }
     }
 
+    fn handle_ipc(&mut self, command: ipc::Command) -> (Result<(), String>, Task<Message>) {
+        match command {
+            ipc::Command::Input { tab, input } => {
+                let Some((id, term)) = self
+                    .tab_order
+                    .get(tab)
+                    .and_then(|id| Some((*id, self.terminals.get(id)?)))
+                else {
+                    return (Err(format!("there is no tab {}", tab + 1)), Task::none());
+                };
+
+                if !term.accepts_input() {
+                    return (
+                        Err(format!("the process of tab {} isn't running", tab + 1)),
+                        Task::none(),
+                    );
+                }
+
+                let task = term
+                    .inject_input(InputSequence::Raw(input))
+                    .map(move |message| Message::LocalTerminal { id, message });
+                (Ok(()), task)
+            }
+            ipc::Command::Keys { tab, keys } => {
+                let Some((id, term)) = self
+                    .tab_order
+                    .get(tab)
+                    .and_then(|id| Some((*id, self.terminals.get_mut(id)?)))
+                else {
+                    return (Err(format!("there is no tab {}", tab + 1)), Task::none());
+                };
+
+                if !term.accepts_input() {
+                    return (
+                        Err(format!("the process of tab {} isn't running", tab + 1)),
+                        Task::none(),
+                    );
+                }
+
+                let tasks: Vec<_> = keys
+                    .into_iter()
+                    .map(|(key, modifiers)| {
+                        term.press_key(frozen_term::KeyPress::new(
+                            key,
+                            modifiers,
+                            keyboard::Location::Standard,
+                        ))
+                    })
+                    .collect();
+                let task =
+                    Task::batch(tasks).map(move |message| Message::LocalTerminal { id, message });
+                (Ok(()), task)
+            }
+            ipc::Command::ToggleRecording { tab } => {
+                let Some(id) = self.tab_order.get(tab).copied() else {
+                    return (Err(format!("there is no tab {}", tab + 1)), Task::none());
+                };
+                (self.toggle_recording(id), Task::none())
+            }
+            ipc::Command::FocusTab { title } => {
+                let Some(id) = self.tab_order.iter().copied().find(|id| {
+                    self.terminals
+                        .get(id)
+                        .is_some_and(|term| term.get_title().contains(&title))
+                }) else {
+                    return (
+                        Err(format!("there is no tab with a title containing {}", title)),
+                        Task::none(),
+                    );
+                };
+
+                self.switch_tab(id);
+                (Ok(()), Task::batch([self.open_window(), self.focus_tab()]))
+            }
+        }
+    }
+
+    /// Starts recording the tab into the recording directory, or stops and flushes its recording.
+    fn toggle_recording(&mut self, id: u32) -> Result<(), String> {
+        let Some(term) = self.terminals.get_mut(&id) else {
+            return Err("there is no such tab".to_string());
+        };
+
+        match term.stop_recording() {
+            Some(Ok(path)) => {
+                eprintln!("Saved recording {}", path.display());
+                Ok(())
+            }
+            Some(Err(err)) => Err(format!("Error saving recording: {}", err)),
+            None => {
+                let timestamp = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let path = self
+                    .config
+                    .recording_dir()
+                    .join(format!("frostbyte-{}-tab-{}.cast", timestamp, id));
+                term.start_recording(&path)
+                    .map_err(|err| format!("Error starting recording {}: {}", path.display(), err))
+            }
+        }
+    }
+
+    /// Shows the number of tabs in the tray tooltip,
+    /// along with the tabs which wrote output that hasn't been seen yet.
+    /// The tabs are listed in the tray menu.
+    /// Only called when tabs, titles or activity changed.
+    fn update_tray(&mut self) {
+        let tabs = self.terminals.len();
+        // the output of the shown tab is seen right away
+        let shown_tab = self.window_id.map(|_| self.selected_tab);
+        let active = self
+            .terminals
+            .iter()
+            .filter(|(id, term)| term.has_activity() && Some(**id) != shown_tab)
+            .count();
+        let tooltip = match (tabs, active) {
+            (0, _) => TRAY_TOOLTIP.to_string(),
+            (1, 0) => format!("{} — 1 tab", TRAY_TOOLTIP),
+            (tabs, 0) => format!("{} — {} tabs", TRAY_TOOLTIP, tabs),
+            (1, active) => format!("{} — 1 tab, {} active", TRAY_TOOLTIP, active),
+            (tabs, active) => format!("{} — {} tabs, {} active", TRAY_TOOLTIP, tabs, active),
+        };
+        let tabs = self
+            .tab_order
+            .iter()
+            .filter_map(|id| Some((*id, self.terminals.get(id)?.get_title().to_string())))
+            .collect();
+        let state = TrayState { tooltip, tabs };
+        if state == self.tray_state {
+            return;
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(sender) = &self.tray_sender {
+            let _ = sender.send(state.clone());
+        }
+        if let Some(tray) = &mut self.tray {
+            tray.apply(state.clone());
+        }
+        self.tray_state = state;
+    }
+
     fn open_window(&mut self) -> Task<Message> {
         if let Some(id) = self.window_id {
             window::gain_focus(id)
@@ -293,20 +1197,36 @@ impl UI {
                     //     ..Default::default()
                     // };
                     let monitor = self.monitor;
+                    let height_offset = self.height_offset;
+                    let configured_position = self.config.position;
+                    let transparent = self.is_transparent();
+                    self.window_transparent = transparent;
+                    // winit only supports blur on Wayland (KWin) and macOS
+                    let blur = cfg!(target_os = "linux") && self.config.blur;
+                    // winit only supports this on Windows, layershell surfaces are never listed
+                    #[cfg(windows)]
+                    let skip_taskbar = self.config.skip_taskbar;
 
                     window::list_monitors().then(move |monitors| {
                         let monitor = monitors
                             .get(monitor)
                             .unwrap_or_else(|| monitors.primary_or_first());
-                        let size = iced::Size::new(
-                            monitor.size().width * 0.8,
-                            monitor.size().height * 0.45,
-                        );
-                        let position = Point::new((monitor.size().width - size.width) / 2.0, 0.0);
+                        let size = winit_window_size(monitor.size(), height_offset);
+                        let position = match configured_position {
+                            WindowPosition::Named(NamedPosition::Center) => {
+                                Point::new((monitor.size().width - size.width) / 2.0, 0.0)
+                            }
+                            WindowPosition::Specific { x, y } => Point::new(x, y),
+                        };
 
-                        let settings = window::Settings {
+                        #[allow(unused_mut)]
+                        let mut settings = window::Settings {
                             decorations: false,
                             resizable: false,
+                            transparent,
+                            blur,
+                            // closing hides the window or quits, see `window_close_button`
+                            exit_on_close_request: false,
                             position: window::Position::Specific(PositionOnMonitor {
                                 monitor_index: Some(monitor.index()),
                                 position: position,
@@ -314,6 +1234,10 @@ impl UI {
                             size,
                             ..Default::default()
                         };
+                        #[cfg(windows)]
+                        {
+                            settings.platform_specific.skip_taskbar = skip_taskbar;
+                        }
 
                         window::open(settings).1.map(Message::WindowOpened)
                     })
@@ -326,13 +1250,18 @@ impl UI {
                 #[cfg(target_os = "linux")]
                 Mode::Layershell => {
                     let id = window::Id::unique();
+                    // the surface keeps stretching to 200 pixels before the right edge
+                    let margin = match self.config.position {
+                        WindowPosition::Named(NamedPosition::Center) => (0, 200, 0, 200),
+                        WindowPosition::Specific { x, y } => (y as i32, 200, 0, x as i32),
+                    };
 
                     self.window_id = Some(id);
                     Task::done(Message::NewLayerShell {
                         settings: NewLayerShellSettings {
                             anchor: Anchor::Top | Anchor::Left | Anchor::Right,
-                            margin: Some((0, 200, 0, 200)),
-                            size: Some((0, 600)),
+                            margin: Some(margin),
+                            size: Some((0, layershell_height(self.height_offset))),
                             ..Default::default()
                         },
                         id,
@@ -342,31 +1271,280 @@ impl UI {
             };
 
             if self.terminals.is_empty() {
-                Task::batch([task, self.open_tab()])
+                let tabs = self.config.initial_tabs.clamp(1, MAX_INITIAL_TABS);
+                let mut tasks = vec![task];
+                // the last opened tab ends up selected
+                for _ in 0..tabs {
+                    tasks.push(self.open_tab());
+                }
+                Task::batch(tasks)
             } else {
+                self.validate_selected_tab();
                 task
             }
         }
     }
 
-    fn close_window(&mut self) -> Task<Message> {
-        if let Some(id) = self.window_id {
-            self.window_id = None;
-            window::close(id)
-        } else {
-            Task::none()
+    fn change_height(&self, delta: f32) -> Task<Message> {
+        let offset = self.height_offset + delta;
+
+        match self.mode {
+            Mode::Winit => {
+                let monitor = self.monitor;
+                window::list_monitors().then(move |monitors| {
+                    let monitor = monitors
+                        .get(monitor)
+                        .unwrap_or_else(|| monitors.primary_or_first());
+                    let base = monitor.size().height * WINIT_HEIGHT_FACTOR;
+                    let offset = offset.clamp(MIN_HEIGHT - base, monitor.size().height - base);
+                    Task::done(Message::UpdateHeightOffset(offset))
+                })
+            }
+            #[cfg(target_os = "linux")]
+            Mode::Layershell => Task::done(Message::UpdateHeightOffset(
+                offset.max(MIN_HEIGHT - LAYERSHELL_HEIGHT),
+            )),
         }
     }
 
-    fn open_tab(&mut self) -> Task<Message> {
-        let style = frozen_term::Style::default().font(Font::with_name("RobotoMono Nerd Font"));
+    fn apply_height(&self) -> Task<Message> {
+        let Some(id) = self.window_id else {
+            return Task::none();
+        };
+
+        match self.mode {
+            Mode::Winit => {
+                let monitor = self.monitor;
+                let height_offset = self.height_offset;
+                window::list_monitors().then(move |monitors| {
+                    let monitor = monitors
+                        .get(monitor)
+                        .unwrap_or_else(|| monitors.primary_or_first());
+                    window::resize(id, winit_window_size(monitor.size(), height_offset))
+                })
+            }
+            #[cfg(target_os = "linux")]
+            Mode::Layershell => Task::done(Message::SizeChange {
+                id,
+                size: (0, layershell_height(self.height_offset)),
+            }),
+        }
+    }
+
+    /// What the close button of the window and close requests of the window manager do
+    fn close_button_message(&self) -> Message {
+        match self.config.window_close_button {
+            WindowCloseButton::Hide => Message::CloseWindow,
+            WindowCloseButton::Quit => Message::Shutdown,
+        }
+    }
+
+    fn close_window(&mut self) -> Task<Message> {
+        if let Some(id) = self.window_id {
+            self.window_id = None;
+            if let Some(term) = self.terminals.get_mut(&self.selected_tab) {
+                // its output until now was seen
+                term.clear_activity();
+                term.set_focused(false);
+            }
+            window::close(id)
+        } else {
+            Task::none()
+        }
+    }
+
+    fn terminal_style(&self) -> frozen_term::Style {
+        let mut style = frozen_term::Style::default()
+            .font(NERD_FONT)
+            .letter_spacing(self.config.letter_spacing.clamp(-5.0, 20.0))
+            .bold_is_bright(self.config.bold_is_bright)
+            .minimum_contrast(self.config.minimum_contrast.clamp(1.0, 21.0));
+        if let Some(theme) = &self.theme_colors {
+            let mut palette = style.palette.0;
+            palette[..16].copy_from_slice(&theme.palette);
+            style = style
+                .foreground_color(theme.foreground)
+                .background_color(theme.background)
+                .palette(std::sync::Arc::new(frozen_term::Palette256(palette)));
+        }
+        if let Some(color) = self.config.cursor_color() {
+            style = style.cursor_color(color);
+        }
+        if let Some(color) = self.config.cursor_text_color() {
+            style = style.cursor_text_color(color);
+        }
+        if self.wallpaper.is_some() {
+            style = style.background_color(iced::Color::TRANSPARENT);
+        } else {
+            let background = style.background_color.scale_alpha(self.opacity());
+            style = style.background_color(background);
+        }
+
+        style
+    }
+
+    /// Shown instead of a terminal while there are no tabs, e.g. with `on_last_tab_close = "keep_open"`
+    fn empty_state(&self) -> Element<'_, Message> {
+        center(
+            column![
+                iced::widget::image(self.logo.clone()).width(96).height(96),
+                text(&self.config.empty_state_message).size(20),
+                button(text("New Tab"))
+                    .padding([8, 20])
+                    .on_press(Message::OpenTab),
+                text("or press Ctrl + Shift + T")
+                    .size(12)
+                    .style(text::secondary),
+            ]
+            .spacing(15)
+            .align_x(Center),
+        )
+        .into()
+    }
+
+    /// Opacity of the window, depending on whether it is focused
+    fn opacity(&self) -> f32 {
+        let focused_opacity = self.opacity_override.unwrap_or(self.config.opacity);
+        let opacity = if self.window_focused {
+            focused_opacity
+        } else {
+            self.config.unfocused_opacity.unwrap_or(focused_opacity)
+        };
+        opacity.clamp(0.0, 1.0)
+    }
+
+    /// Whether the window needs to be created with transparency support
+    fn is_transparent(&self) -> bool {
+        self.opacity_override.unwrap_or(self.config.opacity) < 1.0
+            || self
+                .config
+                .unfocused_opacity
+                .is_some_and(|opacity| opacity < 1.0)
+    }
 
-        let (mut local_terminal, terminal_task) = LocalTerminal::start(self.hotkey.filter());
-        local_terminal.set_style(style);
+    fn change_opacity(&mut self, delta: f32) {
+        let previous = self.opacity();
+        let opacity = (previous + delta).clamp(MIN_OPACITY, 1.0);
+        self.opacity_override = Some(opacity);
+
+        let style = self.terminal_style();
+        for term in self.terminals.values_mut() {
+            term.set_style(style.clone());
+        }
+
+        // winit can't make an existing window transparent
+        if matches!(self.mode, Mode::Winit)
+            && !self.window_transparent
+            && previous >= 1.0
+            && opacity < 1.0
+        {
+            eprintln!("The window isn't transparent, the opacity applies once it is reopened");
+        }
+    }
+
+    /// Whether all tabs fit into the tab bar next to its buttons
+    fn tabs_fit(&self) -> bool {
+        let Some(width) = self.window_width else {
+            return true;
+        };
+        let layout = &self.config.tab_bar;
+        let tabs = self.tab_order.len() as f32;
+        // the tabs, the New Tab button and the pin, monitor and close buttons
+        let needed = tabs * (layout.tab_width + layout.spacing)
+            + layout.new_tab_width
+            + 4.0 * (layout.button_width + layout.spacing);
+        needed <= width
+    }
+
+    fn open_tab(&mut self) -> Task<Message> {
+        self.open_tab_with(
+            Command {
+                login: self.config.login_shell,
+                ..Command::default()
+            },
+            self.config.scrollback_log,
+        )
+    }
+
+    /// Runs the command of the first smart link matching the clicked text
+    fn open_smart_link(&self, line: &str, column: usize) {
+        let Some(command) = self
+            .config
+            .smart_links
+            .iter()
+            .find_map(|link| link.command_at(line, column))
+        else {
+            return;
+        };
+
+        spawn_detached(command, "smart link command");
+    }
+
+    fn open_tab_with(&mut self, command: Command, log_output: bool) -> Task<Message> {
+        if let Some(max_tabs) = self.config.max_tabs
+            && self.terminals.len() >= max_tabs
+        {
+            let notice = self
+                .tab_limit_notice
+                .map_or(0, |notice| notice.wrapping_add(1));
+            self.tab_limit_notice = Some(notice);
+            return Task::future(async move {
+                tokio::time::sleep(TAB_LIMIT_NOTICE_DURATION).await;
+                Message::HideTabLimitNotice(notice)
+            });
+        }
+
+        let default_title = self.config.title_source.title(&command, None);
+        let (mut local_terminal, terminal_task) = LocalTerminal::start_command(
+            Hotkey::filter(self.hotkeys.values().map(|(hotkey, _)| *hotkey).collect()),
+            command,
+        );
+        local_terminal.set_default_title(default_title);
+        local_terminal.set_style(self.terminal_style());
+        local_terminal.set_backspace(self.config.backspace_sends.into());
+        local_terminal.set_scrollback_modifiers(self.config.scrollback_modifier.into());
+        local_terminal.set_enter_sequence(
+            keyboard::Modifiers::SHIFT,
+            self.config.shift_enter.clone().map(String::into_bytes),
+        );
+        local_terminal.set_enter_sequence(
+            keyboard::Modifiers::ALT,
+            self.config.alt_enter.clone().map(String::into_bytes),
+        );
+        local_terminal.set_keep_on_exit(self.config.keep_on_exit);
+        local_terminal.set_auto_restart(self.config.auto_restart.clone().map(Into::into));
+        local_terminal.set_max_fps(self.config.max_fps);
+        local_terminal.set_parse_budget(self.config.parse_budget);
+        local_terminal.set_scrollback_size(self.config.scrollback_lines);
+        local_terminal.set_clipboard_write(self.config.allow_osc52_write);
+        local_terminal.set_local_echo(self.config.local_echo);
+        local_terminal
+            .set_intercept_url_paste(self.config.url_paste_action != UrlPasteAction::Paste);
         let id = self.new_terminal_id;
         self.new_terminal_id += 1;
 
+        if log_output {
+            match scrollback_log::log_path(id)
+                .and_then(|path| OutputLog::create(path, self.config.scrollback_log_max_size))
+            {
+                Ok(log) => local_terminal.set_output_log(Some(log)),
+                Err(err) => eprintln!("Error creating scrollback log: {}", err),
+            }
+        }
+
+        // only the very first tab runs the startup command
+        if id == 1
+            && let Some(command) = &self.config.startup_command
+        {
+            local_terminal.set_startup_command(command.clone());
+        }
+
+        if let Some(previous) = self.terminals.get_mut(&self.selected_tab) {
+            previous.clear_activity();
+        }
         self.terminals.insert(id, local_terminal);
+        self.tab_order.push(id);
+        self.previous_tab = Some(self.selected_tab);
         self.selected_tab = id;
 
         terminal_task.map(move |message| Message::LocalTerminal { id, message })
@@ -381,19 +1559,129 @@ impl UI {
         }
     }
 
+    /// Types the key into the other tabs of the tab's broadcast group.
+    fn broadcast_key(&mut self, id: u32, key_press: frozen_term::KeyPress) -> Task<Message> {
+        let Some(group) = self.tab_groups.get(&id).copied() else {
+            return Task::none();
+        };
+
+        let peers: Vec<u32> = self
+            .tab_groups
+            .iter()
+            .filter(|(peer, peer_group)| **peer != id && **peer_group == group)
+            .map(|(peer, _)| *peer)
+            .collect();
+
+        Task::batch(peers.into_iter().filter_map(|peer| {
+            let task = self
+                .terminals
+                .get_mut(&peer)?
+                .press_key(key_press.clone())
+                .map(move |message| Message::LocalTerminal { id: peer, message });
+            Some(task)
+        }))
+    }
+
     fn close_tab(&mut self, id: u32) -> Task<Message> {
         self.terminals.remove(&id);
+        self.tab_groups.remove(&id);
+        self.tab_order.retain(|tab| *tab != id);
 
-        if let Some((id, _term)) = self.terminals.iter().next() {
+        if let Some(id) = self.tab_order.first() {
             self.selected_tab = *id;
+            if let Some(term) = self.terminals.get_mut(id) {
+                term.clear_activity();
+            }
             Task::none()
         } else {
-            self.close_window()
+            self.last_tab_closed()
+        }
+    }
+
+    fn close_all_tabs(&mut self) -> Task<Message> {
+        if self.config.confirm_quit && !self.busy_tabs().is_empty() {
+            self.confirming = Some(Confirmation::CloseAllTabs);
+            return self.open_window();
+        }
+        self.clear_tabs()
+    }
+
+    fn clear_tabs(&mut self) -> Task<Message> {
+        let ids: Vec<u32> = self.tab_order.drain(..).collect();
+        for id in ids {
+            self.terminals.remove(&id);
+        }
+        self.tab_groups.clear();
+
+        match self.config.on_last_tab_close {
+            // start fresh instead of leaving an empty window behind
+            LastTabClose::KeepOpen if self.window_id.is_some() => self.open_tab(),
+            _ => self.last_tab_closed(),
+        }
+    }
+
+    /// Titles of the tabs running a program in the foreground, e.g. an editor started from the shell
+    fn busy_tabs(&self) -> Vec<&str> {
+        self.tab_order
+            .iter()
+            .filter_map(|id| self.terminals.get(id))
+            .filter(|term| term.runs_foreground_job())
+            .map(|term| term.get_title())
+            .collect()
+    }
+
+    fn confirmation_question(&self, confirmation: Confirmation) -> String {
+        let open = match self.terminals.len() {
+            1 => "1 tab is open".to_string(),
+            count => format!("{count} tabs are open"),
+        };
+        let busy = self.busy_tabs();
+        let state = if busy.is_empty() {
+            open
+        } else {
+            format!("{open}, {} running {}", busy.len(), busy.join(", "))
+        };
+        match confirmation {
+            Confirmation::Quit => format!("{state}. Quit anyway?"),
+            Confirmation::CloseAllTabs => format!("{state}. Close them anyway?"),
         }
     }
 
+    fn last_tab_closed(&mut self) -> Task<Message> {
+        match self.config.on_last_tab_close {
+            LastTabClose::CloseWindow => self.close_window(),
+            LastTabClose::KeepOpen => Task::none(),
+            LastTabClose::Quit => Task::done(Message::Shutdown),
+        }
+    }
+
+    /// The selected tab might have been closed while the window was hidden.
+    /// Falls back to the first available tab in that case.
+    fn validate_selected_tab(&mut self) {
+        if !self.terminals.contains_key(&self.selected_tab)
+            && let Some(id) = self.tab_order.first()
+        {
+            self.selected_tab = *id;
+        }
+    }
+
+    fn selected_position(&self) -> Option<usize> {
+        self.tab_order
+            .iter()
+            .position(|id| *id == self.selected_tab)
+    }
+
     fn switch_tab(&mut self, id: u32) {
-        if let Some(_terminal) = self.terminals.get(&id) {
+        if id != self.selected_tab && self.terminals.contains_key(&id) {
+            // the newly selected terminal reports its focus once it is displayed
+            if let Some(previous) = self.terminals.get_mut(&self.selected_tab) {
+                previous.set_focused(false);
+                previous.clear_activity();
+            }
+            if let Some(selected) = self.terminals.get_mut(&id) {
+                selected.clear_activity();
+            }
+            self.previous_tab = Some(self.selected_tab);
             self.selected_tab = id;
         }
     }
@@ -410,64 +1698,299 @@ impl UI {
                 .padding(10)
                 .into()
             }
-            None => text("terminal closed").into(),
+            None => self.empty_state(),
+        };
+
+        let tab_view: Element<Message> = match &self.wallpaper {
+            Some(wallpaper) => stack![wallpaper.view(), tab_view].into(),
+            None => tab_view,
+        };
+
+        let dim = self.config.unfocused_dim.clamp(0.0, 1.0);
+        let tab_view: Element<Message> = if !self.window_focused && dim > 0.0 {
+            let overlay = container("")
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .style(move |_theme| container::Style {
+                    background: Some(iced::Color::from_rgba(0.0, 0.0, 0.0, dim).into()),
+                    ..Default::default()
+                });
+            stack![tab_view, overlay].into()
+        } else {
+            tab_view
         };
 
-        let tab_bar = row(self.terminals.iter().map(|(id, terminal)| {
+        let tabs = self
+            .tab_order
+            .iter()
+            .enumerate()
+            .filter_map(|(index, id)| Some((index, id, self.terminals.get(id)?)));
+
+        let layout = &self.config.tab_bar;
+        let tab_color = layout.color();
+        let selected_tab_color = layout.selected_color();
+
+        let tab_bar = row(tabs.map(|(index, id, terminal)| {
             let style = if id == &self.selected_tab {
-                button::secondary
+                tab_style(true, selected_tab_color)
             } else {
-                button::primary
+                tab_style(false, tab_color)
             };
-            button(row![
-                center(text(terminal.get_title())),
-                button(text("X").center())
-                    .on_press(Message::CloseTab(id.clone()))
-                    .width(30)
-                    .style(button::danger)
-            ])
-            .on_press(Message::SwitchTab(id.clone()))
-            .style(style)
-            .width(200)
-            .height(Length::Fill)
-            .into()
-        }))
-        .spacing(5);
-
-        column![
-            tab_view,
-            tab_bar
-                .push(
-                    button(center(text("New Tab")))
-                        .width(200)
+            let mut tab = row![];
+            if let Some(group) = self.tab_groups.get(id) {
+                let color = GROUP_COLORS[group % GROUP_COLORS.len()];
+                let name = self
+                    .config
+                    .tab_groups
+                    .get(*group)
+                    .map_or("", String::as_str);
+                tab = tab.push(tooltip(
+                    container(text(""))
+                        .width(4)
                         .height(Length::Fill)
-                        .on_press(Message::OpenTab),
-                )
-                .push(iced::widget::space::horizontal())
-                .push(
-                    button(center(text("<").size(20).align_y(Center)))
-                        .width(40)
+                        .style(move |_| container::Style::default().background(color)),
+                    container(text(name))
+                        .padding(4)
+                        .style(container::rounded_box),
+                    tooltip::Position::Top,
+                ));
+            }
+            if terminal.is_locked() {
+                tab = tab.push(
+                    text(LOCK_ICON)
+                        .font(NERD_FONT)
                         .height(Length::Fill)
-                        .on_press(Message::PreviousMonitor),
-                )
-                .push(
-                    button(center(text(">").size(20).align_y(Center)))
-                        .width(40)
+                        .center(),
+                );
+            }
+            if terminal.is_recording() {
+                tab = tab.push(
+                    text(RECORDING_ICON)
+                        .font(NERD_FONT)
                         .height(Length::Fill)
-                        .on_press(Message::NextMonitor),
-                )
-                .push(
-                    button(center(text("X").size(20).align_y(Center)))
-                        .style(button::danger)
-                        .width(40)
+                        .center()
+                        .style(text::danger),
+                );
+            }
+            if terminal.is_frozen() {
+                tab = tab.push(
+                    text(FROZEN_ICON)
+                        .font(NERD_FONT)
                         .height(Length::Fill)
-                        .on_press(Message::CloseWindow)
+                        .center(),
+                );
+            }
+            let label = match self.config.tab_label {
+                TabLabel::Title => text(terminal.get_title()),
+                TabLabel::Index => text!("{}", index + 1),
+                TabLabel::IndexTitle => text!("{}: {}", index + 1, terminal.get_title()),
+                TabLabel::Icon => text(
+                    self.config
+                        .tab_icon(terminal.get_title(), terminal.command()),
                 )
-                .height(40)
-        ]
-        .height(40)
-        .height(Length::Fill)
-        .into()
+                .font(NERD_FONT),
+            };
+            let mut tab = tab.push(center(label));
+            let show_close_button = match self.config.tab_close_button {
+                TabCloseButton::Always => true,
+                TabCloseButton::OnHover => self.hovered_tab == Some(*id),
+                TabCloseButton::Never => false,
+            };
+            if show_close_button {
+                tab = tab.push(
+                    button(text("X").center())
+                        .on_press(Message::CloseTab(id.clone()))
+                        .width(layout.close_button_width)
+                        .style(button::danger),
+                );
+            }
+
+            mouse_area(
+                button(tab)
+                    .on_press(Message::SwitchTab(id.clone()))
+                    .style(style)
+                    .width(layout.tab_width)
+                    .height(Length::Fill),
+            )
+            .on_middle_press(Message::CloseTab(id.clone()))
+            .on_right_press(Message::CycleTabGroup(*id))
+            .on_enter(Message::HoverTab(Some(*id)))
+            .on_exit(Message::HoverTab(None))
+            .into()
+        }))
+        .spacing(layout.spacing);
+
+        let new_tab_button = button(center(text("New Tab")))
+            .height(Length::Fill)
+            .on_press(Message::OpenTab);
+        // without profiles there is nothing to choose from
+        let new_tab: Element<Message> = if self.config.profiles.is_empty() {
+            new_tab_button.width(layout.new_tab_width).into()
+        } else {
+            row![
+                new_tab_button.width(layout.new_tab_width - layout.close_button_width),
+                button(center(text("\u{f078}").font(NERD_FONT)))
+                    .height(Length::Fill)
+                    .width(layout.close_button_width)
+                    .on_press(Message::ToggleProfileMenu),
+            ]
+            .into()
+        };
+
+        let tab_bar = tab_bar
+            .push(new_tab)
+            .push(iced::widget::space::horizontal())
+            .push(
+                button(center(text(PIN_ICON).font(NERD_FONT)))
+                    .style(if self.pinned {
+                        button::secondary
+                    } else {
+                        button::primary
+                    })
+                    .width(layout.button_width)
+                    .height(Length::Fill)
+                    .on_press(Message::TogglePin),
+            )
+            .push(
+                button(center(text("<").size(20).align_y(Center)))
+                    .width(layout.button_width)
+                    .height(Length::Fill)
+                    .on_press(Message::PreviousMonitor),
+            )
+            .push(
+                button(center(text(">").size(20).align_y(Center)))
+                    .width(layout.button_width)
+                    .height(Length::Fill)
+                    .on_press(Message::NextMonitor),
+            )
+            .push(
+                button(center(text("X").size(20).align_y(Center)))
+                    .style(button::danger)
+                    .width(layout.button_width)
+                    .height(Length::Fill)
+                    .on_press(self.close_button_message()),
+            )
+            .height(layout.height);
+
+        // once the tabs overflow, the wheel is left to scrolling the tab bar
+        let tab_bar: Element<Message> = if self.config.tabbar_scroll_switches && self.tabs_fit() {
+            mouse_area(tab_bar).on_scroll(Message::ScrollTabBar).into()
+        } else {
+            tab_bar.into()
+        };
+
+        let mut content = column![tab_view].height(Length::Fill);
+        if self.show_tab_bar {
+            content = content.push(tab_bar);
+        }
+
+        let content: Element<Message> = if self.profile_menu_open {
+            let profiles = column(self.config.profiles.iter().map(|profile| {
+                button(text(&profile.name))
+                    .width(Length::Fill)
+                    .on_press(Message::OpenTabWithProfile(profile.name.clone()))
+                    .into()
+            }))
+            .spacing(2);
+
+            // open the menu upwards, right above the New Tab button
+            let menu = container(
+                container(profiles)
+                    .padding(4)
+                    .width(200)
+                    .style(container::rounded_box),
+            )
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_y(iced::alignment::Vertical::Bottom)
+            .padding(iced::Padding {
+                top: 0.0,
+                right: 0.0,
+                bottom: layout.height + layout.spacing,
+                left: self.tab_order.len() as f32 * (layout.tab_width + layout.spacing),
+            });
+
+            stack![content, menu].into()
+        } else {
+            content.into()
+        };
+
+        if let Some(confirmation) = self.confirming {
+            let dialog = container(
+                column![
+                    text(self.confirmation_question(confirmation)),
+                    row![
+                        button(text(match confirmation {
+                            Confirmation::Quit => "Quit",
+                            Confirmation::CloseAllTabs => "Close All Tabs",
+                        }))
+                        .style(button::danger)
+                        .on_press(Message::Confirm),
+                        button(text("Cancel")).on_press(Message::CancelConfirmation),
+                    ]
+                    .spacing(10),
+                ]
+                .spacing(20)
+                .align_x(Center),
+            )
+            .padding(20)
+            .style(container::rounded_box);
+
+            stack![content, opaque(center(dialog))].into()
+        } else if let Some((_, url)) = &self.pasted_url {
+            let dialog = container(
+                column![
+                    text("The clipboard contains a URL"),
+                    text(url).style(text::secondary),
+                    row![
+                        button(text("Open in Browser")).on_press(Message::OpenPastedUrl),
+                        button(text("Paste"))
+                            .style(button::secondary)
+                            .on_press(Message::PastePastedUrl),
+                        button(text("Cancel"))
+                            .style(button::secondary)
+                            .on_press(Message::CancelUrlPaste),
+                    ]
+                    .spacing(10),
+                ]
+                .spacing(20)
+                .align_x(Center),
+            )
+            .padding(20)
+            .max_width(600)
+            .style(container::rounded_box);
+
+            stack![content, opaque(center(dialog))].into()
+        } else if self.tab_limit_notice.is_some() {
+            let notice = container(text!(
+                "Reached the limit of {} tabs, close one to open another",
+                self.terminals.len()
+            ))
+            .padding(10)
+            .style(container::rounded_box);
+
+            stack![
+                content,
+                container(notice)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .align_x(Center)
+                    .align_y(iced::alignment::Vertical::Bottom)
+                    .padding(20)
+            ]
+            .into()
+        } else {
+            content.into()
+        }
+    }
+
+    pub fn style(&self, theme: &iced::Theme) -> iced::theme::Style {
+        let palette = theme.palette();
+
+        iced::theme::Style {
+            background_color: palette.background.scale_alpha(self.opacity()),
+            text_color: palette.text,
+        }
     }
 
     pub fn title(&self, _id: window::Id) -> String {
@@ -482,18 +2005,29 @@ impl UI {
     pub fn subscription(&self) -> Subscription<Message> {
         Subscription::batch([
             Subscription::run(poll_events_sub),
+            #[cfg(unix)]
+            Subscription::run(ipc::listen),
+            window::events().filter_map(|(_id, event)| match event {
+                window::Event::Focused => Some(Message::WindowFocused(true)),
+                window::Event::Unfocused => Some(Message::WindowFocused(false)),
+                window::Event::CloseRequested => Some(Message::WindowCloseRequested),
+                window::Event::Opened { size, .. } | window::Event::Resized(size) => {
+                    Some(Message::WindowResized(size))
+                }
+                _ => None,
+            }),
             keyboard::listen().filter_map(|event| {
                 if let keyboard::Event::KeyPressed {
                     key,
-                    modified_key: _,
+                    modified_key,
                     physical_key: _,
-                    location: _,
+                    location,
                     modifiers,
                     text: _,
                     repeat: _,
                 } = event
                 {
-                    match key {
+                    let message = match key {
                         keyboard::Key::Named(keyboard::key::Named::Pause) => None,
                         keyboard::Key::Character(c) => match c.as_str() {
                             "t" | "T" => {
@@ -503,6 +2037,85 @@ impl UI {
                                     None
                                 }
                             }
+                            "l" | "L" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::ToggleLock)
+                                } else {
+                                    None
+                                }
+                            }
+                            "p" | "P" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::TogglePin)
+                                } else {
+                                    None
+                                }
+                            }
+                            "s" | "S" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::ToggleFreeze)
+                                } else {
+                                    None
+                                }
+                            }
+                            "r" | "R" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::ToggleRecording)
+                                } else {
+                                    None
+                                }
+                            }
+                            "i" | "I" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::ToggleControlChars)
+                                } else {
+                                    None
+                                }
+                            }
+                            "y" | "Y" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::CycleTheme)
+                                } else {
+                                    None
+                                }
+                            }
+                            "b" | "B" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::ToggleTabBar)
+                                } else {
+                                    None
+                                }
+                            }
+                            "o" | "O" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::SelectLastOutput)
+                                } else {
+                                    None
+                                }
+                            }
+                            // shift turns the brackets into braces on most layouts
+                            "]" | "}" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::OpacityUp)
+                                } else {
+                                    None
+                                }
+                            }
+                            "[" | "{" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::OpacityDown)
+                                } else {
+                                    None
+                                }
+                            }
+                            // shift turns the backtick into a tilde on most layouts
+                            "`" | "~" => {
+                                if modifiers.control() && modifiers.shift() {
+                                    Some(Message::LastUsedTab)
+                                } else {
+                                    None
+                                }
+                            }
                             _ => None,
                         },
                         keyboard::Key::Named(keyboard::key::Named::ArrowLeft) => {
@@ -520,22 +2133,65 @@ impl UI {
                             }
                         }
                         keyboard::Key::Named(keyboard::key::Named::ArrowUp) => {
-                            if modifiers.control() && modifiers.shift() {
+                            if modifiers.control() && modifiers.alt() {
+                                Some(Message::IncreaseHeight)
+                            } else if modifiers.control() && modifiers.shift() {
                                 Some(Message::NextMonitor)
                             } else {
                                 None
                             }
                         }
                         keyboard::Key::Named(keyboard::key::Named::ArrowDown) => {
-                            if modifiers.control() && modifiers.shift() {
+                            if modifiers.control() && modifiers.alt() {
+                                Some(Message::DecreaseHeight)
+                            } else if modifiers.control() && modifiers.shift() {
                                 Some(Message::PreviousMonitor)
                             } else {
                                 None
                             }
                         }
+                        keyboard::Key::Named(keyboard::key::Named::Space) => {
+                            if modifiers.control() && modifiers.shift() {
+                                Some(Message::LocateCursor)
+                            } else {
+                                None
+                            }
+                        }
+                        keyboard::Key::Named(keyboard::key::Named::PageUp) => {
+                            if modifiers.control() && modifiers.shift() {
+                                Some(Message::MoveTabLeft)
+                            } else {
+                                None
+                            }
+                        }
+                        keyboard::Key::Named(keyboard::key::Named::PageDown) => {
+                            if modifiers.control() && modifiers.shift() {
+                                Some(Message::MoveTabRight)
+                            } else {
+                                None
+                            }
+                        }
+                        keyboard::Key::Named(keyboard::key::Named::Home) => {
+                            if modifiers.control() && modifiers.shift() {
+                                Some(Message::ScrollToLastPrompt)
+                            } else {
+                                None
+                            }
+                        }
+                        keyboard::Key::Named(keyboard::key::Named::Delete) => {
+                            if modifiers.control() && modifiers.shift() {
+                                Some(Message::ResetTerminal)
+                            } else {
+                                None
+                            }
+                        }
                         keyboard::Key::Named(_named) => None,
                         keyboard::Key::Unidentified => None,
-                    }
+                    };
+                    message.map(|message| Message::Shortcut {
+                        message: Box::new(message),
+                        key_press: frozen_term::KeyPress::new(modified_key, modifiers, location),
+                    })
                 } else {
                     None
                 }
@@ -544,6 +2200,83 @@ impl UI {
     }
 }
 
+/// Runs the command in the background, the process is reaped once it exits
+fn spawn_detached(mut command: std::process::Command, description: &str) {
+    match command.spawn() {
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => eprintln!("Error running {}: {}", description, err),
+    }
+}
+
+/// Opens the URL in the default browser
+fn open_url(url: &str) {
+    let command = if cfg!(windows) {
+        let mut command = std::process::Command::new("rundll32");
+        command.args(["url.dll,FileProtocolHandler", url]);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new("open");
+        command.arg(url);
+        command
+    } else {
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(url);
+        command
+    };
+
+    spawn_detached(command, "browser");
+}
+
+/// With `title_source = "cwd"` the title follows the working directory the shell reports
+fn update_cwd_title(title_source: &TitleSource, term: &mut LocalTerminal) {
+    if *title_source == TitleSource::Cwd {
+        term.set_default_title(title_source.title(term.command(), term.current_dir()));
+    }
+}
+
+/// Tabs use the given color or fall back to the theme,
+/// like the primary and secondary buttons.
+fn tab_style(
+    selected: bool,
+    color: Option<iced::Color>,
+) -> impl Fn(&iced::Theme, button::Status) -> button::Style {
+    move |theme, status| {
+        let palette = theme.extended_palette();
+        let pair = if selected {
+            palette.secondary.base
+        } else {
+            palette.primary.base
+        };
+        let base = color.unwrap_or(pair.color);
+
+        let background = match status {
+            button::Status::Hovered => base.scale_alpha(0.8),
+            button::Status::Pressed => base.scale_alpha(0.6),
+            button::Status::Active | button::Status::Disabled => base,
+        };
+
+        button::Style {
+            background: Some(background.into()),
+            text_color: pair.text,
+            border: iced::border::rounded(2),
+            ..button::Style::default()
+        }
+    }
+}
+
+fn winit_window_size(monitor_size: iced::Size, height_offset: f32) -> iced::Size {
+    let height = (monitor_size.height * WINIT_HEIGHT_FACTOR + height_offset)
+        .clamp(MIN_HEIGHT, monitor_size.height);
+    iced::Size::new(monitor_size.width * 0.8, height)
+}
+
+#[cfg(target_os = "linux")]
+fn layershell_height(height_offset: f32) -> u32 {
+    (LAYERSHELL_HEIGHT + height_offset).max(MIN_HEIGHT) as u32
+}
+
 /// Stolen from the tauri global hotkey example for iced
 fn poll_events_sub() -> impl Stream<Item = Message> {
     channel(32, async |mut sender| {
@@ -557,7 +2290,18 @@ fn poll_events_sub() -> impl Stream<Item = Message> {
         #[cfg(target_os = "linux")]
         const SIGUSR1_U: usize = SIGUSR1 as usize;
         #[cfg(target_os = "linux")]
-        signal_flag::register_usize(SIGUSR1, Arc::clone(&flag_counter), SIGUSR1_U).unwrap();
+        if let Err(err) = signal_flag::register_usize(SIGUSR1, Arc::clone(&flag_counter), SIGUSR1_U)
+        {
+            eprintln!("Error registering SIGUSR1 handler: {}", err);
+        }
+        #[cfg(target_os = "linux")]
+        let terminate = Arc::new(AtomicBool::new(false));
+        #[cfg(target_os = "linux")]
+        for signal in [SIGTERM, SIGINT] {
+            if let Err(err) = signal_flag::register(signal, Arc::clone(&terminate)) {
+                eprintln!("Error registering handler for signal {}: {}", signal, err);
+            }
+        }
 
         // poll for global hotkey events every 50ms
         loop {
@@ -568,18 +2312,39 @@ fn poll_events_sub() -> impl Stream<Item = Message> {
                     eprintln!("Error sending hotkey message: {}", err);
                 }
                 flag_counter = Arc::new(AtomicUsize::new(0));
-                signal_flag::register_usize(SIGUSR1, Arc::clone(&flag_counter), SIGUSR1_U).unwrap();
+                if let Err(err) =
+                    signal_flag::register_usize(SIGUSR1, Arc::clone(&flag_counter), SIGUSR1_U)
+                {
+                    eprintln!("Error registering SIGUSR1 handler: {}", err);
+                }
+            }
+            #[cfg(target_os = "linux")]
+            if terminate.swap(false, Ordering::Relaxed) {
+                if let Err(err) = sender.send(Message::Terminate).await {
+                    eprintln!("Error sending terminate message: {}", err);
+                }
             }
 
             if let Ok(event) = hotkey_receiver.try_recv() {
                 if event.state() == HotKeyState::Pressed {
-                    if let Err(err) = sender.send(Message::Hotkey).await {
+                    if let Err(err) = sender.send(Message::GlobalHotkey(event.id())).await {
                         eprintln!("Error sending hotkey message: {}", err);
                     }
                 }
             }
-            if let Ok(_event) = tray_menu_receiver.try_recv() {
-                if let Err(err) = sender.send(Message::Shutdown).await {
+            if let Ok(event) = tray_menu_receiver.try_recv() {
+                let message = match event.id.0.as_str() {
+                    TRAY_CLOSE_ALL_TABS => Some(Message::CloseAllTabs),
+                    TRAY_TMUX => Some(Message::OpenTmux),
+                    TRAY_EXIT => Some(Message::Shutdown),
+                    id => id
+                        .strip_prefix(TRAY_TAB_PREFIX)
+                        .and_then(|id| id.parse().ok())
+                        .map(Message::ShowTab),
+                };
+                if let Some(message) = message
+                    && let Err(err) = sender.send(message).await
+                {
                     eprintln!("Error sending tray message: {}", err);
                 }
             }
@@ -604,85 +2369,154 @@ fn poll_events_sub() -> impl Stream<Item = Message> {
     })
 }
 
-enum Hotkey {
-    #[allow(dead_code)]
-    F12,
-    #[allow(dead_code)]
-    AltF12,
-    Pause,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Default for Hotkey {
-    fn default() -> Self {
-        if std::env::var_os("DEBUG").is_some() {
-            return Self::Pause;
-        }
-        #[cfg(target_os = "linux")]
-        return Self::F12;
-        #[cfg(not(target_os = "linux"))]
-        return Self::AltF12;
+    #[test]
+    fn headless_start() {
+        let (mut ui, _) = UI::start_headless();
+        assert!(ui.tray.is_none() && ui.hotkeys.is_empty());
+
+        let _ = ui.update(Message::OpenTab);
+        let _ = ui.update(Message::OpenTab);
+        assert_eq!(ui.tab_order, [1, 2]);
+        assert_eq!(ui.selected_tab, 2);
     }
-}
 
-impl Hotkey {
-    fn global_hotkey(&self) -> hotkey::HotKey {
-        match self {
-            Self::F12 => hotkey::HotKey::new(None, hotkey::Code::F12),
-            Self::AltF12 => hotkey::HotKey::new(Some(hotkey::Modifiers::ALT), hotkey::Code::F12),
-            Self::Pause => hotkey::HotKey::new(None, hotkey::Code::Pause),
-        }
-    }
-
-    fn iced(&self) -> (iced::keyboard::Key, iced::keyboard::Modifiers) {
-        match self {
-            Self::F12 => (
-                iced::keyboard::Key::Named(iced::keyboard::key::Named::F12),
-                iced::keyboard::Modifiers::empty(),
-            ),
-            Self::AltF12 => (
-                iced::keyboard::Key::Named(iced::keyboard::key::Named::F12),
-                iced::keyboard::Modifiers::ALT,
-            ),
-            Self::Pause => (
-                iced::keyboard::Key::Named(iced::keyboard::key::Named::Pause),
-                iced::keyboard::Modifiers::empty(),
-            ),
-        }
-    }
-
-    fn filter(
-        &self,
-    ) -> impl 'static + Fn(&iced::keyboard::Key, &iced::keyboard::Modifiers) -> bool {
-        let (hotkey, hotkey_modifiers) = self.iced();
-        move |key: &iced::keyboard::Key, modifiers: &iced::keyboard::Modifiers| {
-            if modifiers.control() && modifiers.shift() {
-                match key {
-                    keyboard::Key::Named(named) => match named {
-                        keyboard::key::Named::ArrowLeft => return true,
-                        keyboard::key::Named::ArrowRight => return true,
-                        keyboard::key::Named::ArrowUp => return true,
-                        keyboard::key::Named::ArrowDown => return true,
-                        _ => {}
-                    },
-                    keyboard::Key::Character(character) => match character.as_str() {
-                        "T" => return true,
-                        _ => {}
-                    },
-                    _ => {}
-                }
-            }
-            if key == &iced::keyboard::Key::Character("T".into())
-                && modifiers.control()
-                && modifiers.shift()
-            {
-                return true;
-            };
+    #[test]
+    fn reopening_focuses_an_open_tab() {
+        let (mut ui, _) = UI::start_headless();
+        let _ = ui.update(Message::OpenTab);
+        let _ = ui.update(Message::OpenTab);
+        let _ = ui.update(Message::WindowOpened(window::Id::unique()));
 
-            if key == &hotkey && modifiers == &hotkey_modifiers {
-                return true;
-            }
+        // the selected tab is closed while the window is hidden
+        let _ = ui.update(Message::CloseWindow);
+        let _ = ui.update(Message::CloseTab(2));
+        let _ = ui.update(Message::WindowOpened(window::Id::unique()));
+        assert_eq!(ui.selected_tab, 1);
+
+        // even if its id went stale some other way
+        let _ = ui.update(Message::CloseWindow);
+        ui.selected_tab = 7;
+        let _ = ui.update(Message::WindowOpened(window::Id::unique()));
+        assert_eq!(ui.selected_tab, 1);
+    }
+
+    /// Adds a tab whose output is fed by the test instead of a shell
+    fn scripted_tab(ui: &mut UI) -> u32 {
+        let (terminal, _) = LocalTerminal::scripted(frozen_term::Size { cols: 20, rows: 5 });
+        let id = ui.new_terminal_id;
+        ui.new_terminal_id += 1;
+        ui.terminals.insert(id, terminal);
+        ui.tab_order.push(id);
+        id
+    }
+
+    #[test]
+    fn background_tabs_keep_their_output() {
+        let (mut ui, _) = UI::start_headless();
+        let first = scripted_tab(&mut ui);
+        let second = scripted_tab(&mut ui);
+        let _ = ui.update(Message::SwitchTab(first));
+
+        ui.terminals
+            .get_mut(&second)
+            .unwrap()
+            .feed_output("in the background");
+        let _ = ui.update(Message::SwitchTab(second));
+
+        assert_eq!(ui.selected_tab, second);
+        assert!(
+            ui.terminals[&second]
+                .screen_contents()
+                .starts_with("in the background")
+        );
+        assert!(ui.terminals[&first].screen_contents().trim().is_empty());
+    }
+
+    #[test]
+    fn quitting_asks_with_multiple_tabs() {
+        let (mut ui, _) = UI::start_headless();
+        ui.config.confirm_quit = true;
+
+        scripted_tab(&mut ui);
+        let _ = ui.update(Message::Shutdown);
+        assert_eq!(ui.confirming, None);
 
-            false
+        scripted_tab(&mut ui);
+        let _ = ui.update(Message::Shutdown);
+        assert_eq!(ui.confirming, Some(Confirmation::Quit));
+        assert_eq!(
+            ui.confirmation_question(Confirmation::Quit),
+            "2 tabs are open. Quit anyway?"
+        );
+    }
+
+    #[test]
+    fn shortcuts_leave_control_letters_to_the_shell() {
+        let filter = Hotkey::filter(vec![Hotkey::F12, Hotkey::AltF12, Hotkey::Pause]);
+        for letter in 'a'..='z' {
+            let key = keyboard::Key::Character(letter.to_string().into());
+            assert!(!filter(&key, &keyboard::Modifiers::CTRL), "Ctrl+{letter}");
         }
+
+        let new_tab = keyboard::Key::Character("T".into());
+        assert!(filter(
+            &new_tab,
+            &(keyboard::Modifiers::CTRL | keyboard::Modifiers::SHIFT)
+        ));
+    }
+
+    #[test]
+    fn opacity_shortcuts_keep_the_unfocused_opacity() {
+        let (mut ui, _) = UI::start_headless();
+        ui.config.unfocused_opacity = Some(0.5);
+
+        let _ = ui.update(Message::OpacityDown);
+        assert!((ui.opacity() - (1.0 - OPACITY_STEP)).abs() < 0.001);
+        assert!((ui.terminal_style().background_color.a - ui.opacity()).abs() < 0.001);
+
+        let _ = ui.update(Message::WindowFocused(false));
+        assert_eq!(ui.opacity(), 0.5);
+    }
+
+    #[test]
+    fn cwd_title_follows_the_shell() {
+        let (mut ui, _) = UI::start_headless();
+        ui.config.title_source = TitleSource::Cwd;
+        let id = scripted_tab(&mut ui);
+
+        let term = ui.terminals.get_mut(&id).unwrap();
+        term.feed_output("\x1b]7;file://host/tmp/project\x1b\\");
+        update_cwd_title(&ui.config.title_source, term);
+        assert_eq!(term.get_title(), "project");
+
+        term.feed_output("\x1b]7;file://host/tmp/other\x1b\\");
+        update_cwd_title(&ui.config.title_source, term);
+        assert_eq!(term.get_title(), "other");
+    }
+
+    #[test]
+    fn tray_lists_unseen_output() {
+        let (mut ui, _) = UI::start_headless();
+        let first = scripted_tab(&mut ui);
+        let second = scripted_tab(&mut ui);
+        let _ = ui.update(Message::SwitchTab(first));
+        let _ = ui.update(Message::WindowOpened(window::Id::unique()));
+
+        ui.terminals.get_mut(&second).unwrap().feed_output("output");
+        ui.terminals.get_mut(&first).unwrap().feed_output("output");
+        // fed around the message handler, which would notice the activity
+        ui.tray_outdated = true;
+        let _ = ui.update(Message::LocateCursor);
+        // the output of the shown tab was seen
+        assert!(ui.tray_state.tooltip.ends_with("2 tabs, 1 active"));
+
+        let _ = ui.update(Message::SwitchTab(second));
+        assert!(ui.tray_state.tooltip.ends_with("2 tabs"));
+        assert!(!ui.terminals[&first].has_activity());
+        assert!(!ui.terminals[&second].has_activity());
     }
 }
@@ -0,0 +1,89 @@
+use iced::{
+    Color, ContentFit, Element, Length,
+    widget::{column, container, image, responsive, row, stack},
+};
+
+use crate::{
+    config::{Config, WallpaperMode},
+    ui::Message,
+};
+
+/// Background image rendered behind the terminal.
+///
+/// The image is only read once, iced caches the decoded image for every frame after that.
+pub struct Wallpaper {
+    handle: image::Handle,
+    width: f32,
+    height: f32,
+    mode: WallpaperMode,
+    opacity: f32,
+    overlay_alpha: f32,
+}
+
+impl Wallpaper {
+    pub fn load(config: &Config) -> Option<Self> {
+        let path = config.background_image.as_ref()?;
+
+        let (width, height) = match ::image::image_dimensions(path) {
+            Ok(dimensions) => dimensions,
+            Err(err) => {
+                eprintln!("Error loading background image {}: {}", path.display(), err);
+                return None;
+            }
+        };
+
+        Some(Self {
+            handle: image::Handle::from_path(path),
+            width: width as f32,
+            height: height as f32,
+            mode: config.background_image_mode,
+            opacity: config.background_image_opacity.clamp(0.0, 1.0),
+            overlay_alpha: config.background_overlay_alpha.clamp(0.0, 1.0),
+        })
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let image: Element<Message> = match self.mode {
+            WallpaperMode::Fill => self.image(ContentFit::Cover),
+            WallpaperMode::Fit => self.image(ContentFit::Contain),
+            WallpaperMode::Tile => responsive(|size| {
+                let columns = (size.width / self.width).ceil() as usize;
+                let rows = (size.height / self.height).ceil() as usize;
+
+                column((0..rows).map(|_| {
+                    row((0..columns).map(|_| {
+                        image(self.handle.clone())
+                            .width(self.width)
+                            .height(self.height)
+                            .opacity(self.opacity)
+                            .into()
+                    }))
+                    .into()
+                }))
+                .clip(true)
+                .into()
+            })
+            .into(),
+        };
+
+        // darkens the image to keep the text readable
+        let overlay = container("")
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|_theme| container::Style {
+                background: Some(Color::from_rgba(0.0, 0.0, 0.0, self.overlay_alpha).into()),
+                ..Default::default()
+            });
+
+        stack![image, overlay].into()
+    }
+
+    fn image(&self, content_fit: ContentFit) -> Element<'_, Message> {
+        image(self.handle.clone())
+            .content_fit(content_fit)
+            .opacity(self.opacity)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
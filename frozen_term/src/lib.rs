@@ -9,7 +9,9 @@ mod wezterm;
 pub mod local_terminal;
 
 pub use terminal::{
-    Action, Message, Terminal,
+    Action, Backspace, KeyPress, Message, Terminal,
     style::{CursorShape, Palette256, Style},
 };
 pub use terminal_grid::Size;
+#[cfg(feature = "testing")]
+pub use wezterm::snapshot::SnapshotError;
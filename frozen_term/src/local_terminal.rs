@@ -1,13 +1,24 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use crate::{Style, terminal};
+use crate::{Backspace, KeyPress, Style, terminal};
 use async_pty::PtyProcess;
 use iced::{
-    self, Element, Length, Task,
+    self, Alignment, Element, Length, Task,
     task::sipper,
-    widget::{center, text},
+    widget::{button, center, column, text},
 };
 
+mod cast_recording;
+mod output_log;
+pub use async_pty::{Command, TerminalInput};
+pub use cast_recording::CastRecording;
+pub use output_log::OutputLog;
+
 #[derive(Debug, Clone)]
 pub struct Message(InnerMessage);
 
@@ -15,8 +26,18 @@ pub struct Message(InnerMessage);
 enum InnerMessage {
     Opened(Arc<(PtyProcess, tokio::sync::mpsc::Receiver<Vec<u8>>)>),
     Terminal(terminal::Message),
+    /// A message of the terminal showing the control characters
+    ControlChars(terminal::Message),
     Output(Vec<u8>),
+    /// Parses the next part of the pending output
+    ParseOutput,
     InjectInput(Vec<u8>),
+    /// Writes the next chunk of the pending input
+    WritePending,
+    Failed(String),
+    Restart,
+    /// Restarts the process unless it has already been restarted manually
+    AutoRestart,
     Closed,
 }
 
@@ -24,38 +45,93 @@ pub enum Action {
     Run(Task<Message>),
     IdChanged,
     Close,
+    /// A key has been typed into the terminal
+    KeyPressed(KeyPress),
+    /// See [`terminal::Action::CtrlClick`]
+    CtrlClick {
+        line: String,
+        column: usize,
+    },
+    /// See [`terminal::Action::UrlPasted`]
+    UrlPasted(String),
+    /// The process rang the bell
+    Bell,
     None,
 }
 
+/// Restarts the process automatically once it exits.
+#[derive(Debug, Clone)]
+pub struct AutoRestart {
+    /// Time before the first restart, doubled with every process exiting right after its start
+    pub delay: Duration,
+    /// Shown while waiting for the restart
+    pub message: Option<String>,
+    /// Restarts in a row of processes exiting right after their start, before giving up
+    pub max_attempts: u32,
+}
+
 enum State {
     Starting,
     Active(PtyProcess),
+    /// The process couldn't be started
+    Failed(String),
     Closed,
 }
 
+/// A terminal running a local process in a pty.
+///
+/// The output is processed by the task returned from [`LocalTerminal::start`],
+/// independent of the terminal being displayed. Terminals which aren't shown,
+/// e.g. in a background tab, keep their screen and scrollback up to date.
 pub struct LocalTerminal {
     state: State,
     display: terminal::Terminal,
+    output_log: Option<OutputLog>,
+    recording: Option<CastRecording>,
+    startup_input: Option<Vec<u8>>,
+    command: Command,
+    keep_on_exit: bool,
+    auto_restart: Option<AutoRestart>,
+    /// Start of the current process
+    started_at: Option<Instant>,
+    /// Automatic restarts since the last process which kept running for a while
+    restart_attempts: u32,
+    restart_pending: bool,
+    frame_interval: Duration,
+    parse_budget: usize,
+    /// Output which still has to be parsed, e.g. of a large burst.
+    /// Anything arriving in the meantime is queued behind it.
+    pending_output: VecDeque<u8>,
+    /// Input which still has to be written in chunks, e.g. of a huge paste.
+    /// Anything typed in the meantime is queued behind it.
+    pending_input: VecDeque<u8>,
+    /// Output arrived since [`LocalTerminal::clear_activity`] was called
+    activity: bool,
+    /// Shown in place of the display while control characters are shown.
+    /// Only receives the output in caret notation, the display still parses the output as is.
+    control_chars: Option<(terminal::Terminal, CaretNotation)>,
 }
 
 impl LocalTerminal {
     pub fn start(
         key_filter: impl 'static + Fn(&iced::keyboard::Key, &iced::keyboard::Modifiers) -> bool,
+    ) -> (Self, Task<Message>) {
+        Self::start_command(key_filter, Command::default())
+    }
+
+    /// Like [`LocalTerminal::start`], but runs the given command instead of the default shell.
+    pub fn start_command(
+        key_filter: impl 'static + Fn(&iced::keyboard::Key, &iced::keyboard::Modifiers) -> bool,
+        command: Command,
     ) -> (Self, Task<Message>) {
         let size = async_pty::TerminalSize { cols: 80, rows: 24 };
         let (display, display_task) = terminal::Terminal::new();
         let display = display.key_filter(key_filter);
 
-        let start_task = Task::future(async {
-            let (process, output) = PtyProcess::shell(size).await.unwrap();
-            Message(InnerMessage::Opened(Arc::new((process, output))))
-        });
+        let start_task = Self::spawn(command.clone(), size);
 
         (
-            Self {
-                state: State::Starting,
-                display,
-            },
+            Self::with_display(display, command),
             Task::batch([
                 display_task.map(InnerMessage::Terminal).map(Message),
                 start_task,
@@ -63,25 +139,235 @@ impl LocalTerminal {
         )
     }
 
+    fn with_display(display: terminal::Terminal, command: Command) -> Self {
+        Self {
+            state: State::Starting,
+            display,
+            output_log: None,
+            recording: None,
+            startup_input: None,
+            command,
+            keep_on_exit: false,
+            auto_restart: None,
+            started_at: None,
+            restart_attempts: 0,
+            restart_pending: false,
+            frame_interval: Duration::from_secs(1) / DEFAULT_MAX_FPS,
+            parse_budget: DEFAULT_PARSE_BUDGET,
+            pending_output: VecDeque::new(),
+            pending_input: VecDeque::new(),
+            activity: false,
+            control_chars: None,
+        }
+    }
+
+    /// A terminal without a process, to test it without a display or a shell.
+    ///
+    /// Output is passed in via [`LocalTerminal::feed_output`] and can be checked with
+    /// [`LocalTerminal::screen_contents`] or [`LocalTerminal::dump`].
+    /// Whatever the terminal writes to the process, e.g. typed keys, answers to queries or resizes,
+    /// ends up in the receiver. Keys pressed via [`LocalTerminal::press_key`] are only passed on
+    /// by the next call to [`LocalTerminal::process_events`].
+    #[cfg(feature = "testing")]
+    pub fn scripted(size: crate::Size) -> (Self, tokio::sync::mpsc::Receiver<TerminalInput>) {
+        let mut terminal = Self::with_display(terminal::Terminal::scripted(), Command::default());
+        let (process, input) = PtyProcess::detached();
+        terminal.state = State::Active(process);
+        terminal.started_at = Some(Instant::now());
+        // output is processed right away, there is no runtime to process the rest later
+        terminal.parse_budget = usize::MAX;
+
+        let _ = terminal.resize(size);
+        (terminal, input)
+    }
+
+    /// Processes output as if the process had written it.
+    #[cfg(feature = "testing")]
+    pub fn feed_output(&mut self, output: impl Into<Vec<u8>>) {
+        let _ = self.update(Message(InnerMessage::Output(output.into())));
+        self.process_events();
+    }
+
+    /// Resizes the terminal, the process is informed like with a real pty.
+    #[cfg(feature = "testing")]
+    #[must_use]
+    pub fn resize(&mut self, size: crate::Size) -> Action {
+        let action = self.display.resize(size);
+        self.handle_display_action(action)
+    }
+
+    /// Types the key into the terminal shown, like its widget does,
+    /// so keys taken by the key filter don't reach the process.
+    #[cfg(feature = "testing")]
+    pub fn type_key(&mut self, key_press: KeyPress) {
+        if self.shown().filters_key(&key_press) {
+            return;
+        }
+
+        let _ = if self.control_chars.is_some() {
+            self.press_control_chars_key(key_press)
+        } else {
+            let action = self.display.press_key(key_press);
+            self.handle_display_action(action)
+        };
+        self.process_events();
+    }
+
+    /// Passes the events of a [`LocalTerminal::scripted`] terminal on, e.g. typed keys to the process.
+    #[cfg(feature = "testing")]
+    pub fn process_events(&mut self) {
+        for action in self.display.process_events() {
+            let _ = self.handle_display_action(action);
+        }
+    }
+
+    fn spawn(command: Command, size: async_pty::TerminalSize) -> Task<Message> {
+        Task::future(async {
+            match PtyProcess::spawn(command, size).await {
+                Ok((process, output)) => Message(InnerMessage::Opened(Arc::new((process, output)))),
+                Err(err) => Message(InnerMessage::Failed(err.to_string())),
+            }
+        })
+    }
+
     pub fn style(mut self, style: Style) -> Self {
         self.set_style(style);
         self
     }
 
     pub fn set_style(&mut self, style: Style) {
+        if let Some((control_chars, _)) = &mut self.control_chars {
+            control_chars.set_style(style.clone());
+        }
         self.display.set_style(style);
     }
 
+    pub fn set_backspace(&mut self, backspace: Backspace) {
+        self.display.set_backspace(backspace);
+    }
+
+    /// See [`Terminal::set_scrollback_modifiers`](crate::Terminal::set_scrollback_modifiers).
+    pub fn set_scrollback_modifiers(&mut self, modifiers: iced::keyboard::Modifiers) {
+        self.display.set_scrollback_modifiers(modifiers);
+    }
+
+    /// See [`Terminal::set_enter_sequence`](crate::Terminal::set_enter_sequence).
+    pub fn set_enter_sequence(
+        &mut self,
+        modifiers: iced::keyboard::Modifiers,
+        sequence: Option<Vec<u8>>,
+    ) {
+        self.display.set_enter_sequence(modifiers, sequence);
+    }
+
+    /// Limits how often output is passed to the terminal, which then triggers a redraw.
+    /// Output arriving in between is collected and processed at once.
+    /// Only applies to processes started afterwards.
+    pub fn set_max_fps(&mut self, max_fps: u32) {
+        self.frame_interval = Duration::from_secs(1) / max_fps.max(1);
+    }
+
+    /// Limits how many bytes of output are processed in one go.
+    /// Anything beyond that is processed later, after pending input was handled.
+    pub fn set_parse_budget(&mut self, bytes: usize) {
+        self.parse_budget = bytes.max(1);
+    }
+
+    /// See [`Terminal::set_scrollback_size`](crate::Terminal::set_scrollback_size).
+    pub fn set_scrollback_size(&mut self, lines: usize) {
+        if let Some((control_chars, _)) = &mut self.control_chars {
+            control_chars.set_scrollback_size(lines);
+        }
+        self.display.set_scrollback_size(lines);
+    }
+
+    /// Allows the process to set the clipboard via OSC 52
+    pub fn set_clipboard_write(&mut self, allow: bool) {
+        self.display.set_clipboard_write(allow);
+    }
+
+    /// See [`Terminal::set_local_echo`](crate::Terminal::set_local_echo).
+    pub fn set_local_echo(&mut self, local_echo: bool) {
+        self.display.set_local_echo(local_echo);
+    }
+
+    /// Keeps the terminal open after the process exited, offering to restart it.
+    /// Otherwise [`Action::Close`] is returned once the process exits.
+    pub fn set_keep_on_exit(&mut self, keep_on_exit: bool) {
+        self.keep_on_exit = keep_on_exit;
+    }
+
+    /// Restarts the process after it exited, instead of closing or waiting for [`Action::Close`].
+    /// Processes exiting right after their start are restarted with an increasing delay,
+    /// until `max_attempts` is reached and the terminal shows an error instead.
+    pub fn set_auto_restart(&mut self, auto_restart: Option<AutoRestart>) {
+        self.auto_restart = auto_restart;
+    }
+
+    /// Streams the raw output of the terminal into the given log.
+    /// Passing `None` stops logging.
+    pub fn set_output_log(&mut self, log: Option<OutputLog>) {
+        self.output_log = log;
+    }
+
+    /// Starts recording the output into an asciinema `.cast` file at the given path,
+    /// replacing any running recording.
+    pub fn start_recording(&mut self, path: impl Into<PathBuf>) -> std::io::Result<()> {
+        let recording = CastRecording::create(path, self.display.size())?;
+        if let Some(previous) = self.recording.replace(recording) {
+            previous.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Stops the recording and returns the path of the finished file, if there was a recording.
+    pub fn stop_recording(&mut self) -> Option<std::io::Result<PathBuf>> {
+        self.recording.take().map(CastRecording::finish)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Whether the process started another program in the foreground, e.g. the shell an editor.
+    /// Always `false` where job control isn't available.
+    pub fn runs_foreground_job(&self) -> bool {
+        match &self.state {
+            State::Active(pty) => pty.runs_foreground_job(),
+            _ => false,
+        }
+    }
+
+    /// Runs the command in the shell once it has been started.
+    /// Has no effect if the shell is already running.
+    pub fn set_startup_command(&mut self, command: String) {
+        let mut input = command.into_bytes();
+        input.push(b'\n');
+        self.startup_input = Some(input);
+    }
+
     #[must_use]
     pub fn update(&mut self, message: Message) -> Action {
         match message.0 {
             InnerMessage::Opened(arc) => {
                 let (process, output) = Arc::into_inner(arc).unwrap();
 
-                let stream = sipper(|mut sender| async move {
+                let frame_interval = self.frame_interval;
+                let stream = sipper(move |mut sender| async move {
                     let mut output = output;
-                    while let Some(chunk) = output.recv().await {
+                    let mut next_frame = tokio::time::Instant::now();
+                    while let Some(mut chunk) = output.recv().await {
+                        // Coalesce everything arriving until the next frame is due,
+                        // so a flood of output is parsed in large batches and redrawn once per frame.
+                        while chunk.len() < MAX_BATCH_SIZE {
+                            match tokio::time::timeout_at(next_frame, output.recv()).await {
+                                Ok(Some(more)) => chunk.extend(more),
+                                Ok(None) | Err(_) => break,
+                            }
+                        }
+
                         sender.send(InnerMessage::Output(chunk)).await;
+                        next_frame = tokio::time::Instant::now() + frame_interval;
                     }
 
                     sender.send(InnerMessage::Closed).await;
@@ -89,61 +375,369 @@ impl LocalTerminal {
 
                 let task = Task::stream(stream).map(Message);
 
+                // the shell reads this as soon as it is ready for input
+                if let Some(input) = self.startup_input.take() {
+                    let _ = process.try_write(input);
+                }
+
                 self.state = State::Active(process);
+                self.started_at = Some(Instant::now());
 
-                Action::Run(task)
+                // input which arrived while starting
+                if self.pending_input.is_empty() {
+                    Action::Run(task)
+                } else {
+                    Action::Run(Task::batch([
+                        task,
+                        Task::done(Message(InnerMessage::WritePending)),
+                    ]))
+                }
             }
             InnerMessage::Terminal(message) => {
+                let key_press = message.key_press();
                 let action = self.display.update(message);
 
-                match action {
-                    terminal::Action::None => Action::None,
-                    terminal::Action::Run(task) => {
-                        Action::Run(task.map(InnerMessage::Terminal).map(Message))
-                    }
-                    terminal::Action::IdChanged => Action::IdChanged,
-                    terminal::Action::Input(input) => {
-                        if let State::Active(pty) = &self.state {
-                            pty.try_write(input).unwrap();
-                        }
-                        Action::None
-                    }
-                    terminal::Action::Resize(size) => {
-                        if let State::Active(pty) = &self.state {
-                            pty.try_resize(async_pty::TerminalSize {
-                                rows: size.rows as u16,
-                                cols: size.cols as u16,
-                            })
-                            .unwrap();
-                        }
-                        Action::None
+                match (self.handle_display_action(action), key_press) {
+                    (Action::None, Some(key_press)) => Action::KeyPressed(key_press),
+                    (action, _) => action,
+                }
+            }
+            InnerMessage::ControlChars(message) => {
+                let Some((control_chars, _)) = &mut self.control_chars else {
+                    return Action::None;
+                };
+                let key_press = message.key_press();
+                let action = match &key_press {
+                    Some(key_press) => self.press_control_chars_key(key_press.clone()),
+                    None => {
+                        let action = control_chars.update(message);
+                        self.handle_control_chars_action(action)
                     }
+                };
+
+                match (action, key_press) {
+                    (Action::None, Some(key_press)) => Action::KeyPressed(key_press),
+                    (action, _) => action,
                 }
             }
-            InnerMessage::InjectInput(input) => {
-                if let State::Active(pty) = &self.state {
-                    pty.try_write(input).unwrap();
+            InnerMessage::InjectInput(input) => self.write_input(input),
+            InnerMessage::WritePending => {
+                let State::Active(pty) = &self.state else {
+                    // kept for the process being started, written once it runs
+                    if !matches!(self.state, State::Starting) {
+                        self.pending_input.clear();
+                    }
+                    return Action::None;
+                };
+
+                let length = self.pending_input.len().min(INPUT_CHUNK_SIZE);
+                let chunk: Vec<u8> = self.pending_input.range(..length).copied().collect();
+                // the chunk is retried with the next one if the pty is still busy
+                if pty.try_write(chunk).is_ok() {
+                    self.pending_input.drain(..length);
+                }
+
+                if self.pending_input.is_empty() {
+                    Action::None
+                } else {
+                    Action::Run(Self::write_pending_later())
                 }
-                Action::None
             }
             InnerMessage::Output(output) => {
-                self.display.advance_bytes(output);
+                self.activity = true;
+
+                if let Some(log) = &mut self.output_log
+                    && let Err(err) = log.write(&output)
+                {
+                    eprintln!(
+                        "Error writing output log {}, disabling it: {}",
+                        log.path().display(),
+                        err
+                    );
+                    self.output_log = None;
+                }
+
+                if let Some(recording) = &mut self.recording
+                    && let Err(err) = recording.write_output(&output)
+                {
+                    eprintln!(
+                        "Error writing recording {}, stopping it: {}",
+                        recording.path().display(),
+                        err
+                    );
+                    self.recording = None;
+                }
+
+                // a scheduled ParseOutput already takes care of the queue
+                let was_pending = !self.pending_output.is_empty();
+                self.pending_output.extend(output);
+                if was_pending {
+                    Action::None
+                } else {
+                    self.parse_output()
+                }
+            }
+            InnerMessage::ParseOutput => self.parse_output(),
+            InnerMessage::Failed(err) => {
+                eprintln!("Error starting terminal process: {}", err);
+                self.state = State::Failed(err);
 
                 Action::None
             }
+            InnerMessage::AutoRestart if !self.restart_pending => Action::None,
+            InnerMessage::Restart | InnerMessage::AutoRestart => {
+                if matches!(self.state, State::Active(_) | State::Starting) {
+                    return Action::None;
+                }
+
+                self.restart_pending = false;
+                self.state = State::Starting;
+                let size = self.display.size();
+                Action::Run(Self::spawn(
+                    self.command.clone(),
+                    async_pty::TerminalSize {
+                        rows: size.rows as u16,
+                        cols: size.cols as u16,
+                    },
+                ))
+            }
             InnerMessage::Closed => {
                 self.state = State::Closed;
+                self.pending_input.clear();
+
+                if let Some(auto_restart) = &self.auto_restart {
+                    let quick_exit = self
+                        .started_at
+                        .is_some_and(|started_at| started_at.elapsed() < QUICK_EXIT);
+                    self.restart_attempts = if quick_exit {
+                        self.restart_attempts + 1
+                    } else {
+                        1
+                    };
+
+                    if self.restart_attempts > auto_restart.max_attempts {
+                        let err = format!(
+                            "the process exited right after starting {} times in a row",
+                            self.restart_attempts
+                        );
+                        eprintln!("Not restarting terminal process: {}", err);
+                        self.state = State::Failed(err);
+                        return Action::None;
+                    }
+
+                    let backoff = 2u32.saturating_pow(self.restart_attempts - 1);
+                    let delay = auto_restart
+                        .delay
+                        .saturating_mul(backoff)
+                        .min(MAX_RESTART_DELAY);
+                    self.restart_pending = true;
+                    return Action::Run(Task::future(async move {
+                        tokio::time::sleep(delay).await;
+                        Message(InnerMessage::AutoRestart)
+                    }));
+                }
+
+                if self.keep_on_exit {
+                    Action::None
+                } else {
+                    Action::Close
+                }
+            }
+        }
+    }
+
+    /// Parses up to the parse budget of the pending output.
+    /// Large bursts are processed piece by piece, so pending input like Ctrl+C is
+    /// handled in between instead of waiting for the whole burst.
+    fn parse_output(&mut self) -> Action {
+        let length = self.pending_output.len().min(self.parse_budget);
+        let output: Vec<u8> = self.pending_output.drain(..length).collect();
+
+        if let Some((control_chars, caret_notation)) = &mut self.control_chars {
+            control_chars.advance_bytes(caret_notation.convert(&output));
+        }
+        self.display.advance_bytes(output);
+
+        if self.pending_output.is_empty() {
+            Action::None
+        } else {
+            Action::Run(Task::done(Message(InnerMessage::ParseOutput)))
+        }
+    }
+
+    /// Shortcuts act on the control characters shown, other keys are encoded by the display,
+    /// which follows the modes set by the process.
+    fn press_control_chars_key(&mut self, key_press: KeyPress) -> Action {
+        let Some((control_chars, _)) = &mut self.control_chars else {
+            return Action::None;
+        };
+
+        match control_chars.handle_shortcut(&key_press) {
+            Some(action) => self.handle_control_chars_action(action),
+            None => {
+                let action = self.display.encode_key(key_press);
+                self.handle_display_action(action)
+            }
+        }
+    }
 
-                Action::Close
+    fn handle_control_chars_action(&mut self, action: terminal::Action) -> Action {
+        match action {
+            terminal::Action::Run(task) => {
+                Action::Run(task.map(InnerMessage::ControlChars).map(Message))
             }
+            // the display and the process follow the size of whatever is shown
+            terminal::Action::Resize(size) => {
+                let action = self.display.resize(size);
+                self.handle_display_action(action)
+            }
+            action => self.handle_display_action(action),
+        }
+    }
+
+    fn handle_display_action(&mut self, action: terminal::Action) -> Action {
+        match action {
+            terminal::Action::None => Action::None,
+            terminal::Action::Run(task) => {
+                Action::Run(task.map(InnerMessage::Terminal).map(Message))
+            }
+            terminal::Action::IdChanged => Action::IdChanged,
+            terminal::Action::CtrlClick { line, column } => Action::CtrlClick { line, column },
+            terminal::Action::UrlPasted(url) => Action::UrlPasted(url),
+            terminal::Action::Bell => Action::Bell,
+            terminal::Action::Input(input) => self.write_input(input),
+            terminal::Action::Resize(size) => {
+                if let Some(recording) = &mut self.recording
+                    && let Err(err) = recording.write_resize(size)
+                {
+                    eprintln!(
+                        "Error writing recording {}, stopping it: {}",
+                        recording.path().display(),
+                        err
+                    );
+                    self.recording = None;
+                }
+                if let State::Active(pty) = &self.state {
+                    pty.try_resize(async_pty::TerminalSize {
+                        rows: size.rows as u16,
+                        cols: size.cols as u16,
+                    })
+                    .unwrap();
+                }
+                Action::None
+            }
+        }
+    }
+
+    /// Writes input to the process, large input like huge pastes is written in chunks
+    /// so neither the pty nor the UI get stuck on it.
+    fn write_input(&mut self, input: Vec<u8>) -> Action {
+        let pty = match &self.state {
+            State::Active(pty) => pty,
+            // written once the process is started
+            State::Starting => {
+                self.pending_input.extend(input);
+                return Action::None;
+            }
+            State::Failed(_) | State::Closed => return Action::None,
+        };
+
+        if self.pending_input.is_empty()
+            && input.len() <= INPUT_CHUNK_SIZE
+            && pty.try_write(input.clone()).is_ok()
+        {
+            return Action::None;
+        }
+
+        // keep the order of everything written while chunks are pending
+        let was_pending = !self.pending_input.is_empty();
+        self.pending_input.extend(input);
+        if was_pending {
+            Action::None
+        } else {
+            Action::Run(Task::done(Message(InnerMessage::WritePending)))
+        }
+    }
+
+    fn write_pending_later() -> Task<Message> {
+        Task::future(async {
+            tokio::time::sleep(INPUT_CHUNK_DELAY).await;
+            Message(InnerMessage::WritePending)
+        })
+    }
+
+    /// See [`Terminal::set_intercept_url_paste`](crate::Terminal::set_intercept_url_paste).
+    pub fn set_intercept_url_paste(&mut self, intercept: bool) {
+        self.display.set_intercept_url_paste(intercept);
+    }
+
+    /// Pastes the text into the terminal, like pasting from the clipboard.
+    #[must_use]
+    pub fn paste(&mut self, text: &str) -> Task<Message> {
+        let action = self.display.paste_text(text);
+        match self.handle_display_action(action) {
+            Action::Run(task) => task,
+            _ => Task::none(),
+        }
+    }
+
+    /// Handles a keypress from another terminal as if it had been typed into this one.
+    #[must_use]
+    pub fn press_key(&mut self, key_press: KeyPress) -> Task<Message> {
+        let action = self.display.press_key(key_press);
+        match self.handle_display_action(action) {
+            Action::Run(task) => task,
+            _ => Task::none(),
         }
     }
 
     pub fn view<'a>(&'a self) -> Element<'a, Message> {
         match &self.state {
             State::Starting => center(text!("opening pty...")).into(),
-            State::Active(_) => self.display.view().map(InnerMessage::Terminal).map(Message),
-            State::Closed => center(text!("pty closed")).height(Length::Fill).into(),
+            State::Active(_) => match &self.control_chars {
+                Some((control_chars, _)) => control_chars
+                    .view()
+                    .map(InnerMessage::ControlChars)
+                    .map(Message),
+                None => self.display.view().map(InnerMessage::Terminal).map(Message),
+            },
+            State::Failed(err) => center(
+                column![
+                    text!("failed to start: {}", err),
+                    button("Restart").on_press(Message(InnerMessage::Restart)),
+                ]
+                .spacing(10)
+                .align_x(Alignment::Center),
+            )
+            .height(Length::Fill)
+            .into(),
+            State::Closed if self.restart_pending => {
+                let message = self
+                    .auto_restart
+                    .as_ref()
+                    .and_then(|auto_restart| auto_restart.message.as_deref())
+                    .unwrap_or("[process exited, restarting...]");
+                center(
+                    column![
+                        text(message),
+                        button("Restart now").on_press(Message(InnerMessage::Restart)),
+                    ]
+                    .spacing(10)
+                    .align_x(Alignment::Center),
+                )
+                .height(Length::Fill)
+                .into()
+            }
+            State::Closed => center(
+                column![
+                    text!("[process exited]"),
+                    button("Restart").on_press(Message(InnerMessage::Restart)),
+                ]
+                .spacing(10)
+                .align_x(Alignment::Center),
+            )
+            .height(Length::Fill)
+            .into(),
         }
     }
 
@@ -151,12 +745,163 @@ impl LocalTerminal {
         self.display.get_title()
     }
 
+    /// See [`Terminal::current_dir`](crate::Terminal::current_dir).
+    pub fn current_dir(&self) -> Option<std::path::PathBuf> {
+        self.display.current_dir()
+    }
+
+    /// The command the terminal was started with
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    /// See [`Terminal::set_default_title`](crate::Terminal::set_default_title).
+    pub fn set_default_title(&mut self, title: Option<String>) {
+        self.display.set_default_title(title);
+    }
+
+    /// A frozen terminal stops updating its display, while the process keeps running.
+    /// Its output is still processed, so queries are answered, but only shown once the terminal
+    /// is unfrozen. The view stays where it is, except when scrolled.
+    ///
+    /// Unlike flow control via Ctrl+S/Ctrl+Q, this never stops the process.
+    pub fn set_frozen(&mut self, frozen: bool) {
+        if let Some((control_chars, _)) = &mut self.control_chars {
+            control_chars.set_frozen(frozen);
+        }
+        self.display.set_frozen(frozen);
+    }
+
+    /// Whether input reaches a process, either right away or once it has been started.
+    pub fn accepts_input(&self) -> bool {
+        matches!(self.state, State::Starting | State::Active(_))
+    }
+
+    /// Whether the process wrote output since the last call to [`LocalTerminal::clear_activity`],
+    /// e.g. to mark tabs with output the user hasn't seen yet.
+    pub fn has_activity(&self) -> bool {
+        self.activity
+    }
+
+    pub fn clear_activity(&mut self) {
+        self.activity = false;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.display.is_frozen()
+    }
+
+    /// Displays control characters in the output in caret notation like `cat -v`, e.g. `^[` for
+    /// escape, instead of interpreting them. The output is still interpreted as usual in the background,
+    /// so queries are answered and switching back shows the screen as the application left it.
+    /// Only applies to output arriving afterwards, logs and recordings still receive the output as is.
+    #[must_use]
+    pub fn set_show_control_chars(&mut self, show: bool) -> Task<Message> {
+        if !show {
+            self.control_chars = None;
+            return Task::none();
+        }
+        if self.control_chars.is_some() {
+            return Task::none();
+        }
+
+        let (control_chars, task) = self.display.companion();
+        self.control_chars = Some((control_chars, CaretNotation::default()));
+        Task::batch([
+            task.map(InnerMessage::ControlChars).map(Message),
+            self.focus(),
+        ])
+    }
+
+    /// See [`Terminal::key_filter`](crate::Terminal::key_filter).
+    pub fn set_key_filter(
+        &mut self,
+        key_filter: impl 'static + Fn(&iced::keyboard::Key, &iced::keyboard::Modifiers) -> bool,
+    ) {
+        self.display.set_key_filter(key_filter);
+        if let Some((control_chars, _)) = &mut self.control_chars {
+            control_chars.share_key_filter(&self.display);
+        }
+    }
+
+    pub fn shows_control_chars(&self) -> bool {
+        self.control_chars.is_some()
+    }
+
+    /// The terminal which is currently shown
+    fn shown(&self) -> &terminal::Terminal {
+        match &self.control_chars {
+            Some((control_chars, _)) => control_chars,
+            None => &self.display,
+        }
+    }
+
+    /// A locked terminal drops all keyboard input, but still displays output
+    /// and allows scrolling, selecting and copying.
+    pub fn set_locked(&mut self, locked: bool) {
+        if let Some((control_chars, _)) = &mut self.control_chars {
+            control_chars.set_read_only(locked);
+        }
+        self.display.set_read_only(locked);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.display.is_read_only()
+    }
+
+    /// See [`Terminal::dump`](crate::Terminal::dump).
+    #[cfg(feature = "testing")]
+    pub fn dump(&self) -> Vec<u8> {
+        self.display.dump()
+    }
+
+    /// Briefly flashes a ring around the cursor.
+    /// See [`Terminal::locate_cursor`](crate::Terminal::locate_cursor).
+    pub fn locate_cursor(&mut self) {
+        self.display.locate_cursor();
+    }
+
+    /// Scrolls the prompt of the last command to the top.
+    /// See [`Terminal::scroll_to_last_prompt`](crate::Terminal::scroll_to_last_prompt).
+    pub fn scroll_to_last_prompt(&mut self) {
+        self.display.scroll_to_last_prompt();
+    }
+
+    /// Resets the terminal, clearing the screen and the scrollback.
+    /// See [`Terminal::reset`](crate::Terminal::reset).
+    pub fn reset(&mut self) {
+        self.display.reset();
+    }
+
+    /// Selects the output of the last command.
+    /// See [`Terminal::select_last_output`](crate::Terminal::select_last_output).
+    pub fn select_last_output(&mut self) -> bool {
+        self.display.select_last_output()
+    }
+
+    /// Reports focus changes to the application, if it enabled focus reporting.
+    /// See [`Terminal::set_focused`](crate::Terminal::set_focused).
+    pub fn set_focused(&mut self, focused: bool) {
+        self.display.set_focused(focused);
+    }
+
+    /// Returns the visible screen as plain text, ignoring the scroll position.
+    /// Shows the control characters while they are shown, see [`LocalTerminal::set_show_control_chars`].
+    pub fn screen_contents(&self) -> String {
+        self.shown().screen_contents()
+    }
+
+    /// Returns the text of a single cell of the visible screen.
+    pub fn cell_at(&self, row: usize, col: usize) -> Option<String> {
+        self.shown().cell_at(row, col)
+    }
+
     #[must_use]
     pub fn focus<T>(&self) -> Task<T>
     where
         T: Send + 'static,
     {
-        self.display.focus()
+        self.shown().focus()
     }
 
     /// !!!WARNING!!!
@@ -165,8 +910,23 @@ impl LocalTerminal {
     /// If the user has typed something, that input will still be there!
     /// When writing commands manually, you'll need to ensure that they are not influenced by what the user has typed
     /// and you will also have to handle key encoding and control characters yourself.
+    ///
+    /// Input injected while the process is starting is written once it runs,
+    /// see [`LocalTerminal::accepts_input`].
     #[must_use]
     pub fn inject_input(&self, input: InputSequence) -> Task<Message> {
+        if let State::Starting = self.state {
+            // there is nothing to abort yet
+            let input = match input {
+                InputSequence::Raw(input) | InputSequence::AbortAndRaw(input) => input,
+                InputSequence::AbortAndCommand(mut input) => {
+                    input.push('\n');
+                    input.into_bytes()
+                }
+            };
+            return Task::done(Message(InnerMessage::InjectInput(input)));
+        }
+
         if let State::Active(ref pty) = self.state {
             match input {
                 InputSequence::Raw(input) => {
@@ -199,6 +959,47 @@ impl LocalTerminal {
 }
 
 const INJECTION_DELAY: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_FPS: u32 = 60;
+const DEFAULT_PARSE_BUDGET: usize = 64 * 1024;
+/// Processes exiting earlier count as failed restart attempts
+const QUICK_EXIT: Duration = Duration::from_secs(3);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+/// Input larger than this is written in chunks of this size
+const INPUT_CHUNK_SIZE: usize = 16 * 1024;
+/// Pause between the chunks of large input, to let the process catch up
+const INPUT_CHUNK_DELAY: Duration = Duration::from_millis(2);
+/// Output is passed on once this many bytes have been collected, even before the next frame
+const MAX_BATCH_SIZE: usize = 1024 * 1024;
+
+/// Replaces control characters with their caret notation, e.g. `^[` for escape and `^I` for tab.
+/// Line breaks are kept, as the pty turns every `\n` into `\r\n`, only a lone `\r` shows up as `^M`.
+#[derive(Default)]
+struct CaretNotation {
+    /// The last output ended with `\r`, which is a line break if the next output starts with `\n`
+    pending_carriage_return: bool,
+}
+
+impl CaretNotation {
+    fn convert(&mut self, output: &[u8]) -> Vec<u8> {
+        let mut visible = Vec::with_capacity(output.len());
+        let mut bytes = output.iter().copied().peekable();
+        if std::mem::take(&mut self.pending_carriage_return) && bytes.peek() != Some(&b'\n') {
+            visible.extend_from_slice(b"^M");
+        }
+
+        while let Some(byte) = bytes.next() {
+            match byte {
+                b'\r' if bytes.peek() == Some(&b'\n') => {}
+                b'\r' if bytes.peek().is_none() => self.pending_carriage_return = true,
+                b'\n' => visible.extend_from_slice(b"\r\n"),
+                0x00..=0x1f => visible.extend_from_slice(&[b'^', byte + b'@']),
+                0x7f => visible.extend_from_slice(b"^?"),
+                byte => visible.push(byte),
+            }
+        }
+        visible
+    }
+}
 
 pub enum InputSequence {
     /// !!!WARNING!!!
@@ -215,3 +1016,25 @@ pub enum InputSequence {
     /// Be aware that your command will not be sanitized!.
     AbortAndCommand(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caret_notation() {
+        let mut caret_notation = CaretNotation::default();
+        assert_eq!(
+            caret_notation.convert(b"a\x1b[1mb\tc\x7f\r\nd\re"),
+            b"a^[[1mb^Ic^?\r\nd^Me"
+        );
+    }
+
+    #[test]
+    fn caret_notation_split_line_break() {
+        let mut caret_notation = CaretNotation::default();
+        assert_eq!(caret_notation.convert(b"a\r"), b"a");
+        assert_eq!(caret_notation.convert(b"\nb\r"), b"\r\nb");
+        assert_eq!(caret_notation.convert(b"c"), b"^Mc");
+    }
+}
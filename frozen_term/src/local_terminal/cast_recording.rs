@@ -0,0 +1,143 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
+};
+
+use crate::Size;
+
+/// Records the output of a terminal along with its timing into an asciinema v2 `.cast` file.
+///
+/// Output is buffered, [`CastRecording::finish`] has to be called to flush it.
+pub struct CastRecording {
+    path: PathBuf,
+    file: BufWriter<File>,
+    started: Instant,
+    /// The end of the last output, if it stopped in the middle of a UTF-8 character
+    incomplete: Vec<u8>,
+}
+
+impl CastRecording {
+    pub fn create(path: impl Into<PathBuf>, size: Size) -> std::io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = BufWriter::new(File::create(&path)?);
+
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        writeln!(
+            file,
+            r#"{{"version": 2, "width": {}, "height": {}, "timestamp": {}}}"#,
+            size.cols, size.rows, timestamp
+        )?;
+
+        Ok(Self {
+            path,
+            file,
+            started: Instant::now(),
+            incomplete: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write_output(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.incomplete.extend_from_slice(bytes);
+
+        let complete = match std::str::from_utf8(&self.incomplete) {
+            // a character split between chunks is written once it is complete
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => self.incomplete.len(),
+        };
+        let rest = self.incomplete.split_off(complete);
+        let text = String::from_utf8_lossy(&self.incomplete).into_owned();
+        self.incomplete = rest;
+
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.write_event("o", &text)
+    }
+
+    pub fn write_resize(&mut self, size: Size) -> std::io::Result<()> {
+        self.write_event("r", &format!("{}x{}", size.cols, size.rows))
+    }
+
+    /// Flushes the recording, nothing is written afterwards.
+    pub fn finish(mut self) -> std::io::Result<PathBuf> {
+        if !self.incomplete.is_empty() {
+            let text = String::from_utf8_lossy(&self.incomplete).into_owned();
+            self.write_event("o", &text)?;
+        }
+        self.file.flush()?;
+
+        Ok(self.path)
+    }
+
+    fn write_event(&mut self, kind: &str, data: &str) -> std::io::Result<()> {
+        let time = self.started.elapsed().as_secs_f64();
+        writeln!(
+            self.file,
+            r#"[{:.6}, "{}", "{}"]"#,
+            time,
+            kind,
+            escape_json(data)
+        )
+    }
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            char if (char as u32) < 0x20 || char == '\u{7f}' => {
+                escaped.push_str(&format!("\\u{:04x}", char as u32));
+            }
+            char => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(
+            escape_json("\x1b[1m\"a\\b\"\r\n\t\x07\x7fä"),
+            r#"\u001b[1m\"a\\b\"\r\n\t\u0007\u007fä"#
+        );
+    }
+
+    #[test]
+    fn split_characters_are_written_whole() {
+        let path =
+            std::env::temp_dir().join(format!("frozen_term-cast-{}.cast", std::process::id()));
+        let mut recording = CastRecording::create(&path, Size { cols: 80, rows: 24 }).unwrap();
+        let bytes = "aä".as_bytes();
+        recording.write_output(&bytes[..2]).unwrap();
+        recording.write_output(&bytes[2..]).unwrap();
+        recording.finish().unwrap();
+
+        let cast = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let events: Vec<_> = cast.lines().skip(1).collect();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].ends_with(r#", "o", "a"]"#), "{cast}");
+        assert!(events[1].ends_with(r#", "o", "ä"]"#), "{cast}");
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Streams the raw output of a terminal into a file.
+///
+/// Once the file grows beyond `max_size`, it is moved to `<path>.1` and a new file is started,
+/// so at most two files worth of output are kept.
+/// On unix the files are only readable by the current user, as the output may contain secrets.
+pub struct OutputLog {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_size: u64,
+}
+
+impl OutputLog {
+    pub fn create(path: impl Into<PathBuf>, max_size: u64) -> std::io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = create_private(&path)?;
+
+        Ok(Self {
+            path,
+            file,
+            written: 0,
+            max_size,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        if self.written > 0 && self.written + bytes.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        std::fs::rename(&self.path, rotated)?;
+
+        self.file = create_private(&self.path)?;
+        self.written = 0;
+
+        Ok(())
+    }
+}
+
+fn create_private(path: &Path) -> std::io::Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
@@ -1,4 +1,7 @@
-use std::time::{Duration, Instant};
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use iced::{
     Rectangle, Size, Vector,
@@ -10,8 +13,8 @@ use iced::{
 use crate::{
     Style,
     scrollbar::Scrollbar,
-    terminal_grid::{PreRenderer, TerminalGrid, VisiblePosition},
-    wezterm::{WeztermGrid, prerenderer::WeztermPreRenderer},
+    terminal_grid::{PreRenderer, SpanUnderline, TerminalGrid, UnderlineStyle, VisiblePosition},
+    wezterm::{DEFAULT_SCROLLBACK_SIZE, GridEvent, WeztermGrid, prerenderer::WeztermPreRenderer},
 };
 
 pub mod style;
@@ -20,19 +23,67 @@ use style::CursorShape;
 #[derive(Debug, Clone)]
 pub struct Message(InnerMessage);
 
+impl Message {
+    /// Returns the keypress if this message is one.
+    /// Can be used to mirror keypresses into other terminals via [`Terminal::press_key`].
+    pub fn key_press(&self) -> Option<KeyPress> {
+        match &self.0 {
+            InnerMessage::KeyPress {
+                modified_key,
+                modifiers,
+                location,
+            } => Some(KeyPress {
+                modified_key: modified_key.clone(),
+                modifiers: *modifiers,
+                location: *location,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A keypress received by a terminal
+#[derive(Debug, Clone)]
+pub struct KeyPress {
+    modified_key: iced::keyboard::key::Key,
+    modifiers: iced::keyboard::Modifiers,
+    location: iced::keyboard::Location,
+}
+
+impl KeyPress {
+    /// A keypress the terminal didn't receive itself, e.g. one taken by the application as a shortcut
+    pub fn new(
+        modified_key: iced::keyboard::key::Key,
+        modifiers: iced::keyboard::Modifiers,
+        location: iced::keyboard::Location,
+    ) -> Self {
+        Self {
+            modified_key,
+            modifiers,
+            location,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 enum InnerMessage {
     Resize(crate::terminal_grid::Size),
     KeyPress {
         modified_key: iced::keyboard::key::Key,
         modifiers: iced::keyboard::Modifiers,
+        location: iced::keyboard::Location,
     },
     Input(Vec<u8>),
+    ClipboardWrite(String),
+    Bell,
     Paste(Option<String>),
+    PasteAndRun(Option<String>),
     Scrolled(ScrollDelta),
     ScrollTo(usize),
+    ScrollToBottom,
     ScrollDone,
     StartSelection(VisiblePosition),
+    CtrlClick(VisiblePosition),
     MoveSelection(VisiblePosition),
     EndSelection,
     ShowContextMenu(iced::Point),
@@ -40,6 +91,21 @@ enum InnerMessage {
     ContextMenuCopy,
     ContextMenuPaste,
     IdChanged,
+    FocusChanged(bool),
+}
+
+/// Larger clipboard writes via OSC 52 are ignored
+const MAX_CLIPBOARD_WRITE: usize = 1024 * 1024;
+
+/// The byte sent by the backspace key.
+/// Only applies without modifiers, Ctrl+Backspace always sends `0x08`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backspace {
+    /// `0x7f`, expected by most systems
+    #[default]
+    Del,
+    /// `0x08`
+    Bs,
 }
 
 pub enum Action {
@@ -48,22 +114,112 @@ pub enum Action {
     Resize(crate::terminal_grid::Size),
     Input(Vec<u8>),
     IdChanged,
+    /// The text was clicked while holding Ctrl, e.g. to open links.
+    /// `column` is the index of the clicked character in `line`.
+    CtrlClick {
+        line: String,
+        column: usize,
+    },
+    /// The pasted text is a single URL and wasn't pasted,
+    /// see [`Terminal::set_intercept_url_paste`].
+    UrlPasted(String),
+    /// The application rang the bell (BEL)
+    Bell,
 }
 
 pub struct Terminal {
     grid: WeztermGrid,
     id: Id,
-    key_filter: Option<Box<dyn Fn(&iced::keyboard::Key, &iced::keyboard::Modifiers) -> bool>>,
+    /// Shared with the [`Terminal::companion`]
+    key_filter: Option<Rc<dyn Fn(&iced::keyboard::Key, &iced::keyboard::Modifiers) -> bool>>,
     // here to abort the task on drop
     context_menu_position: Option<iced::Point>,
     style: Style,
+    /// Incremented with every style change, so the widget renders the cells again
+    style_version: usize,
+    read_only: bool,
+    /// The widget keeps showing the rendered rows, see [`Terminal::set_frozen`]
+    frozen: bool,
+    scrollback_size: usize,
+    backspace: Backspace,
+    /// Held with PageUp and PageDown to scroll instead of sending the keys
+    scrollback_modifiers: iced::keyboard::Modifiers,
+    clipboard_write: bool,
+    intercept_url_paste: bool,
+    /// Sequences sent by Enter with modifiers instead of the usual one
+    enter_sequences: Vec<(iced::keyboard::Modifiers, Vec<u8>)>,
+    locate_cursor_since: Option<Instant>,
+    /// Shown while the application hasn't set a title
+    default_title: Option<String>,
+    local_echo: bool,
+    /// Typed characters the process hasn't echoed yet
+    predicted: String,
+    /// The process echoed the last typed characters, so predictions are shown.
+    /// Reset by Enter and other control keys, e.g. to not reveal passwords typed afterwards.
+    echo_confirmed: bool,
+    /// Events of a scripted terminal, processed by [`Terminal::process_events`] instead of a task
+    #[cfg(feature = "testing")]
+    scripted_events: Option<iced::futures::stream::BoxStream<'static, GridEvent>>,
     _handle: iced::task::Handle,
 }
 
 impl Terminal {
     pub fn new() -> (Self, iced::Task<Message>) {
         let (grid, stream) = WeztermGrid::new();
-        let (task, handle) = iced::Task::run(stream, InnerMessage::Input)
+        Self::with_grid(grid, stream)
+    }
+
+    /// A terminal without a task processing its events, e.g. answers to queries or typed keys.
+    /// They are processed by [`Terminal::process_events`] instead, so tests can run without a runtime.
+    #[cfg(feature = "testing")]
+    pub fn scripted() -> Self {
+        use iced::futures::StreamExt;
+
+        let (grid, stream) = WeztermGrid::new();
+        let (mut terminal, _) = Self::with_grid(grid, iced::futures::stream::empty());
+        terminal.scripted_events = Some(stream.boxed());
+        terminal
+    }
+
+    /// Processes the events of a [`Terminal::scripted`] terminal which are ready right now.
+    #[cfg(feature = "testing")]
+    #[must_use]
+    pub fn process_events(&mut self) -> Vec<Action> {
+        use iced::futures::{FutureExt, StreamExt};
+
+        let mut messages = Vec::new();
+        if let Some(events) = &mut self.scripted_events {
+            while let Some(Some(event)) = events.next().now_or_never() {
+                messages.push(Self::event_message(event));
+            }
+        }
+
+        messages
+            .into_iter()
+            .map(|message| self.update(Message(message)))
+            .collect()
+    }
+
+    /// Restores a terminal from a snapshot created by [`Terminal::dump`].
+    #[cfg(feature = "testing")]
+    pub fn load(snapshot: &[u8]) -> Result<(Self, iced::Task<Message>), crate::SnapshotError> {
+        let (grid, stream) = WeztermGrid::load(snapshot)?;
+        Ok(Self::with_grid(grid, stream))
+    }
+
+    /// Serializes the scrollback, screen and cursor including all cell attributes,
+    /// e.g. for snapshot tests or to attach the state to a bug report.
+    /// The format is only guaranteed to load in the same version.
+    #[cfg(feature = "testing")]
+    pub fn dump(&self) -> Vec<u8> {
+        self.grid.dump()
+    }
+
+    fn with_grid(
+        grid: WeztermGrid,
+        stream: impl iced::futures::Stream<Item = GridEvent> + Send + 'static,
+    ) -> (Self, iced::Task<Message>) {
+        let (task, handle) = iced::Task::run(stream, Self::event_message)
             .map(Message)
             .abortable();
 
@@ -76,12 +232,55 @@ impl Terminal {
                 key_filter: None,
                 context_menu_position: None,
                 style: Style::default(),
+                style_version: 0,
+                read_only: false,
+                frozen: false,
+                scrollback_size: DEFAULT_SCROLLBACK_SIZE,
+                backspace: Backspace::default(),
+                scrollback_modifiers: iced::keyboard::Modifiers::SHIFT,
+                clipboard_write: false,
+                intercept_url_paste: false,
+                enter_sequences: Vec::new(),
+                locate_cursor_since: None,
+                default_title: None,
+                local_echo: false,
+                predicted: String::new(),
+                echo_confirmed: false,
+                #[cfg(feature = "testing")]
+                scripted_events: None,
                 _handle: handle,
             },
             task,
         )
     }
 
+    /// An empty terminal to show in place of this one, e.g. with a transformed version of the output.
+    /// It starts out with the same size, style and keyboard settings.
+    pub(crate) fn companion(&self) -> (Self, iced::Task<Message>) {
+        let (grid, stream) = WeztermGrid::new();
+        let (mut terminal, task) = Self::with_grid(grid, stream);
+        terminal.grid.resize(self.grid.get_size());
+        terminal.style = self.style.clone();
+        terminal.share_key_filter(self);
+        terminal.read_only = self.read_only;
+        terminal.backspace = self.backspace;
+        terminal.scrollback_modifiers = self.scrollback_modifiers;
+        terminal.enter_sequences = self.enter_sequences.clone();
+        terminal.clipboard_write = self.clipboard_write;
+        terminal.intercept_url_paste = self.intercept_url_paste;
+        terminal.set_frozen(self.frozen);
+        terminal.set_scrollback_size(self.scrollback_size);
+        (terminal, task)
+    }
+
+    fn event_message(event: GridEvent) -> InnerMessage {
+        match event {
+            GridEvent::Input(input) => InnerMessage::Input(input),
+            GridEvent::Clipboard(content) => InnerMessage::ClipboardWrite(content),
+            GridEvent::Bell => InnerMessage::Bell,
+        }
+    }
+
     pub fn id(mut self, id: impl Into<Id>) -> Self {
         self.id = id.into();
         self
@@ -93,7 +292,11 @@ impl Terminal {
     }
 
     pub fn set_style(&mut self, style: Style) {
-        self.style = style;
+        // the widget renders all cells again on a new version
+        if self.style != style {
+            self.style = style;
+            self.style_version = self.style_version.wrapping_add(1);
+        }
     }
 
     /// Allows you to add a filter to stop the terminal from capturing keypresses you want to use for your application.
@@ -110,7 +313,186 @@ impl Terminal {
         &mut self,
         key_filter: impl 'static + Fn(&iced::keyboard::Key, &iced::keyboard::Modifiers) -> bool,
     ) {
-        self.key_filter = Some(Box::new(key_filter));
+        self.key_filter = Some(Rc::new(key_filter));
+    }
+
+    /// Uses the key filter of the other terminal, e.g. one shown in its place.
+    pub(crate) fn share_key_filter(&mut self, other: &Terminal) {
+        self.key_filter = other.key_filter.clone();
+    }
+
+    /// Whether the key filter keeps the keypress from the terminal, see [`Terminal::key_filter`].
+    pub(crate) fn filters_key(&self, key_press: &KeyPress) -> bool {
+        self.key_filter
+            .as_ref()
+            .is_some_and(|filter| filter(&key_press.modified_key, &key_press.modifiers))
+    }
+
+    /// A read only terminal drops all keyboard input and pastes.
+    /// Output is still displayed and scrolling, selecting and copying keep working.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Instead of pasting a single URL, [`Action::UrlPasted`] is returned,
+    /// e.g. to offer opening it. It can still be pasted via [`Terminal::paste_text`].
+    pub fn set_intercept_url_paste(&mut self, intercept: bool) {
+        self.intercept_url_paste = intercept;
+    }
+
+    /// Pastes the text as if it came from the clipboard, using bracketed paste if enabled.
+    #[must_use]
+    pub fn paste_text(&mut self, text: &str) -> Action {
+        if self.read_only {
+            return Action::None;
+        }
+
+        match self.grid.paste(text) {
+            Some(input) => Action::Input(input),
+            None => Action::None,
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// A frozen terminal keeps displaying what it showed when it was frozen, until it is scrolled.
+    /// Output is still processed, e.g. to answer queries, and shows up once unfrozen.
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+        self.grid.set_frozen(frozen);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Lines kept in the scrollback of the primary screen, 3500 by default.
+    /// The alternate screen of full screen applications never adds to it.
+    pub fn set_scrollback_size(&mut self, lines: usize) {
+        if self.scrollback_size != lines {
+            self.scrollback_size = lines;
+            self.grid.set_scrollback_size(lines);
+        }
+    }
+
+    pub fn backspace(mut self, backspace: Backspace) -> Self {
+        self.set_backspace(backspace);
+        self
+    }
+
+    pub fn set_backspace(&mut self, backspace: Backspace) {
+        self.backspace = backspace;
+    }
+
+    /// PageUp and PageDown scroll by a page while exactly these modifiers are held, Shift by default.
+    /// Otherwise the keys are sent to the application. With no modifiers, plain PageUp and PageDown scroll.
+    pub fn set_scrollback_modifiers(&mut self, modifiers: iced::keyboard::Modifiers) {
+        self.scrollback_modifiers = modifiers;
+    }
+
+    /// Overrides the sequence sent by Enter while exactly the given modifiers are held,
+    /// e.g. a newline for Shift+Enter to continue a multi-line command.
+    /// `None` restores the default. Enter without modifiers can't be changed.
+    pub fn set_enter_sequence(
+        &mut self,
+        modifiers: iced::keyboard::Modifiers,
+        sequence: Option<Vec<u8>>,
+    ) {
+        if modifiers.is_empty() {
+            return;
+        }
+        self.enter_sequences
+            .retain(|(existing, _)| *existing != modifiers);
+        if let Some(sequence) = sequence {
+            self.enter_sequences.push((modifiers, sequence));
+        }
+    }
+
+    /// Allows applications to set the clipboard via OSC 52, disabled by default.
+    /// Reading the clipboard via OSC 52 is never supported.
+    pub fn set_clipboard_write(&mut self, allow: bool) {
+        self.clipboard_write = allow;
+    }
+
+    /// Shows typed characters right away, before the process echoed them, e.g. over slow ssh connections.
+    /// The prediction is drawn dimmed and underlined after the cursor until the echo arrives,
+    /// and is dropped if the output doesn't match. Nothing is predicted on the alternate screen,
+    /// or for the first characters typed after Enter until the process echoed one of them.
+    pub fn set_local_echo(&mut self, local_echo: bool) {
+        self.local_echo = local_echo;
+        self.clear_predictions();
+    }
+
+    fn predict(&mut self, key: &iced::keyboard::Key, modifiers: iced::keyboard::Modifiers) {
+        use iced::keyboard::{Key, key::Named};
+
+        if !self.local_echo {
+            return;
+        }
+        // full screen applications draw the input wherever they like
+        if self.grid.is_alt_screen() {
+            self.clear_predictions();
+            return;
+        }
+
+        let plain = !modifiers.control() && !modifiers.alt() && !modifiers.logo();
+        match key {
+            Key::Character(text) if plain && !text.contains(char::is_control) => {
+                self.predicted.push_str(text);
+            }
+            Key::Named(Named::Space) if plain => self.predicted.push(' '),
+            Key::Named(Named::Shift | Named::Control | Named::Alt | Named::Super) => {}
+            // Enter, Backspace, arrows and the like can't be predicted
+            _ => self.clear_predictions(),
+        }
+    }
+
+    /// Drops the predicted characters which were echoed, or all of them on a misprediction
+    fn reconcile_predictions(&mut self, output: &[u8]) {
+        if self.predicted.is_empty() {
+            return;
+        }
+
+        // the echo may be interleaved with escape sequences, e.g. by syntax highlighting
+        let output = strip_escape_sequences(&String::from_utf8_lossy(output));
+        if output.is_empty() {
+            return;
+        }
+
+        // the echo has to start right at the cursor, where the prediction is drawn
+        let echoed: usize = self
+            .predicted
+            .chars()
+            .zip(output.chars())
+            .take_while(|(predicted, output)| predicted == output)
+            .map(|(predicted, _)| predicted.len_utf8())
+            .sum();
+
+        if echoed == 0 {
+            self.clear_predictions();
+        } else {
+            self.predicted.drain(..echoed);
+            self.echo_confirmed = true;
+        }
+    }
+
+    fn clear_predictions(&mut self) {
+        self.predicted.clear();
+        self.echo_confirmed = false;
+    }
+
+    /// Briefly flashes a ring around the cursor, to find it on large screens.
+    pub fn locate_cursor(&mut self) {
+        self.locate_cursor_since = Some(Instant::now());
+    }
+
+    /// How far the cursor flash has progressed, from `0.0` to `1.0`
+    fn locate_cursor_progress(&self) -> Option<f32> {
+        let elapsed = self.locate_cursor_since?.elapsed();
+        (elapsed < LOCATE_CURSOR_DURATION)
+            .then(|| elapsed.as_secs_f32() / LOCATE_CURSOR_DURATION.as_secs_f32())
     }
 
     pub fn focus<T>(&self) -> iced::Task<T>
@@ -129,14 +511,90 @@ impl Terminal {
         ))
     }
 
+    /// The title set by the application, or the default title if it hasn't set one.
     pub fn get_title(&self) -> &str {
-        self.grid.get_title()
+        match &self.default_title {
+            Some(title) if !self.grid.has_title() => title,
+            _ => self.grid.get_title(),
+        }
+    }
+
+    /// The working directory reported by the shell via OSC 7, if it does so.
+    pub fn current_dir(&self) -> Option<std::path::PathBuf> {
+        self.grid.current_dir()
+    }
+
+    /// The title to report while the application hasn't set one,
+    /// `None` reports whatever the terminal emulation uses by default.
+    pub fn set_default_title(&mut self, title: Option<String>) {
+        self.default_title = title;
+    }
+
+    /// Reports focus changes to the application, if it enabled focus reporting (`CSI ? 1004 h`).
+    ///
+    /// The widget takes care of this on its own while it is displayed.
+    /// Use this for terminals which are hidden while focused, e.g. when switching tabs.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.grid.set_focused(focused);
+    }
+
+    /// Returns the visible screen as plain text, ignoring the scroll position.
+    /// Each row is terminated by a newline and stripped of trailing whitespace.
+    pub fn screen_contents(&self) -> String {
+        self.grid.screen_contents()
+    }
+
+    /// Scrolls to the previous or next prompt, if the shell marks them via OSC 133.
+    /// Otherwise scrolls by a single line.
+    pub fn jump_to_prompt(&mut self, previous: bool) {
+        if !self.grid.jump_to_prompt(previous) {
+            self.grid.scroll(if previous { 1 } else { -1 });
+        }
+    }
+
+    /// Scrolls the prompt of the last command to the top, if the shell marks prompts via OSC 133.
+    /// Otherwise scrolls to the current screen.
+    pub fn scroll_to_last_prompt(&mut self) {
+        if !self.grid.scroll_to_last_prompt() {
+            self.grid.scroll_to_bottom();
+        }
+    }
+
+    /// Resets the terminal like the `reset` command (RIS), clearing the screen and the scrollback.
+    /// Recovers from programs which left the terminal in a broken state.
+    pub fn reset(&mut self) {
+        self.clear_predictions();
+        self.grid.reset();
+    }
+
+    /// Selects the output of the last command, if the shell marks it via OSC 133.
+    /// Returns `false` and leaves the selection alone otherwise.
+    pub fn select_last_output(&mut self) -> bool {
+        self.grid.select_last_output()
+    }
+
+    /// The size of the grid in cells
+    pub fn size(&self) -> crate::Size {
+        self.grid.get_size()
+    }
+
+    /// Returns the text of a single cell of the visible screen.
+    pub fn cell_at(&self, row: usize, col: usize) -> Option<String> {
+        self.grid.cell_at(row, col)
+    }
+
+    /// Resizes the grid as if the widget was laid out at this size,
+    /// the widget takes care of this on its own while it is displayed.
+    #[must_use]
+    pub fn resize(&mut self, size: crate::Size) -> Action {
+        self.update(Message(InnerMessage::Resize(size)))
     }
 
     pub fn advance_bytes<B>(&mut self, bytes: B)
     where
         B: AsRef<[u8]>,
     {
+        self.reconcile_predictions(bytes.as_ref());
         self.grid.advance_bytes(bytes.as_ref());
     }
 
@@ -150,36 +608,62 @@ impl Terminal {
             InnerMessage::KeyPress {
                 modified_key,
                 modifiers,
+                location,
             } => {
-                if modified_key == iced::keyboard::Key::Character("V".into())
-                    && modifiers.control()
-                    && modifiers.shift()
-                {
-                    return self.paste();
+                let key_press = KeyPress {
+                    modified_key,
+                    modifiers,
+                    location,
+                };
+                self.handle_shortcut(&key_press)
+                    .unwrap_or_else(|| self.encode_key(key_press))
+            }
+            InnerMessage::Input(input) => Action::Input(input),
+            InnerMessage::ClipboardWrite(content) => {
+                if self.clipboard_write && content.len() <= MAX_CLIPBOARD_WRITE {
+                    Action::Run(iced::clipboard::write(content))
+                } else {
+                    Action::None
                 }
+            }
+            InnerMessage::Bell => Action::Bell,
+            InnerMessage::Paste(paste) => {
+                let Some(paste) = paste else {
+                    return Action::None;
+                };
 
-                if modified_key == iced::keyboard::Key::Character("C".into())
-                    && modifiers.control()
-                    && modifiers.shift()
+                if self.intercept_url_paste && is_url(&paste) {
+                    return Action::UrlPasted(paste.trim().to_string());
+                }
+                self.paste_text(&paste)
+            }
+            InnerMessage::PasteAndRun(paste) => {
+                let Some(paste) = paste else {
+                    return Action::None;
+                };
+
+                if self.intercept_url_paste && is_url(&paste) {
+                    return Action::UrlPasted(paste.trim().to_string());
+                }
+                let mut input = self.grid.paste(&paste).unwrap_or_default();
+                // Multi-line pastes are never run automatically,
+                // they have to be confirmed by pressing enter.
+                if !paste.trim_end().contains('\n')
+                    && let Some(enter) = self.grid.press_key(
+                        iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter),
+                        iced::keyboard::Modifiers::empty(),
+                        iced::keyboard::Location::Standard,
+                    )
                 {
-                    return self.copy();
+                    input.extend(enter);
                 }
 
-                if let Some(input) = self.grid.press_key(modified_key, modifiers) {
-                    Action::Input(input)
-                } else {
+                if input.is_empty() {
                     Action::None
+                } else {
+                    Action::Input(input)
                 }
             }
-            InnerMessage::Input(input) => Action::Input(input),
-            InnerMessage::Paste(paste) => {
-                if let Some(paste) = paste {
-                    if let Some(input) = self.grid.paste(&paste) {
-                        return Action::Input(input);
-                    }
-                }
-                Action::None
-            }
             InnerMessage::Scrolled(scrolled) => {
                 match scrolled {
                     ScrollDelta::Lines { y, .. } => {
@@ -196,7 +680,15 @@ impl Terminal {
                 self.grid.scroll_to(y);
                 Action::None
             }
+            InnerMessage::ScrollToBottom => {
+                self.grid.scroll_to_bottom();
+                Action::Run(self.focus())
+            }
             InnerMessage::ScrollDone => Action::Run(self.focus()),
+            InnerMessage::CtrlClick(position) => match self.grid.visible_line(&position) {
+                Some((line, column)) => Action::CtrlClick { line, column },
+                None => Action::None,
+            },
             InnerMessage::StartSelection(start) => {
                 self.grid.start_selection(start);
                 Action::None
@@ -226,6 +718,134 @@ impl Terminal {
                 self.paste()
             }
             InnerMessage::IdChanged => Action::IdChanged,
+            InnerMessage::FocusChanged(focused) => {
+                self.grid.set_focused(focused);
+                Action::None
+            }
+        }
+    }
+
+    /// The default background and foreground colors,
+    /// swapped while the application enabled reverse video (DECSCNM).
+    fn default_colors(&self) -> (iced::Color, iced::Color) {
+        let background = self.style.background_color;
+        let foreground = self.style.foreground_color;
+        if self.grid.reverse_video() {
+            // keep the transparency of the background
+            (
+                iced::Color {
+                    a: background.a,
+                    ..foreground
+                },
+                iced::Color {
+                    a: 1.0,
+                    ..background
+                },
+            )
+        } else {
+            (background, foreground)
+        }
+    }
+
+    /// Handles the keypress as if it had been typed into this terminal.
+    #[must_use]
+    pub fn press_key(&mut self, key_press: KeyPress) -> Action {
+        self.update(Message(InnerMessage::KeyPress {
+            modified_key: key_press.modified_key,
+            modifiers: key_press.modifiers,
+            location: key_press.location,
+        }))
+    }
+
+    /// Handles the keys the terminal uses itself, e.g. to copy, paste or scroll.
+    pub(crate) fn handle_shortcut(&mut self, key_press: &KeyPress) -> Option<Action> {
+        let KeyPress {
+            modified_key,
+            modifiers,
+            ..
+        } = key_press;
+
+        // Plain Ctrl+letter always reaches the process, only Ctrl+Shift is taken.
+        // Some layouts don't uppercase the key while Ctrl is held,
+        // which would otherwise send Ctrl+C to the process instead of copying.
+        if is_character(modified_key, "v") && modifiers.control() && modifiers.shift() {
+            if modifiers.alt() {
+                return Some(self.paste_and_run());
+            }
+            return Some(self.paste());
+        }
+
+        if is_character(modified_key, "c") && modifiers.control() && modifiers.shift() {
+            return Some(self.copy());
+        }
+
+        if is_character(modified_key, "z") && modifiers.control() && modifiers.shift() {
+            self.jump_to_prompt(true);
+            return Some(Action::None);
+        }
+
+        if is_character(modified_key, "x") && modifiers.control() && modifiers.shift() {
+            self.jump_to_prompt(false);
+            return Some(Action::None);
+        }
+
+        if let iced::keyboard::Key::Named(
+            named @ (iced::keyboard::key::Named::PageUp | iced::keyboard::key::Named::PageDown),
+        ) = modified_key
+            && *modifiers == self.scrollback_modifiers
+        {
+            // keeps the last line of the previous page visible
+            let page = self.grid.get_size().rows.saturating_sub(1).max(1) as isize;
+            if *named == iced::keyboard::key::Named::PageUp {
+                self.grid.scroll(page);
+            } else {
+                self.grid.scroll(-page);
+            }
+            return Some(Action::None);
+        }
+
+        None
+    }
+
+    /// Sends the key to the process, encoded according to the modes it has set.
+    pub(crate) fn encode_key(&mut self, key_press: KeyPress) -> Action {
+        if self.read_only {
+            return Action::None;
+        }
+
+        let KeyPress {
+            modified_key,
+            modifiers,
+            location,
+        } = key_press;
+
+        if modified_key == iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter)
+            && let Some((_, sequence)) = self
+                .enter_sequences
+                .iter()
+                .find(|(enter_modifiers, _)| *enter_modifiers == modifiers)
+        {
+            self.grid.send_input(sequence.clone());
+            return Action::None;
+        }
+
+        // Sent as a character to keep the order with the other keypresses,
+        // which also pass through the grid.
+        let modified_key = if self.backspace == Backspace::Bs
+            && modifiers.is_empty()
+            && modified_key == iced::keyboard::Key::Named(iced::keyboard::key::Named::Backspace)
+        {
+            iced::keyboard::Key::Character("\x08".into())
+        } else {
+            modified_key
+        };
+
+        // the grid sends the input on its own, so the prediction is based on the key
+        self.predict(&modified_key, modifiers);
+        if let Some(input) = self.grid.press_key(modified_key, modifiers, location) {
+            Action::Input(input)
+        } else {
+            Action::None
         }
     }
 
@@ -238,6 +858,10 @@ impl Terminal {
     }
 
     fn paste(&self) -> Action {
+        if self.read_only {
+            return Action::Run(self.focus());
+        }
+
         Action::Run(
             iced::clipboard::read()
                 .map(InnerMessage::Paste)
@@ -246,6 +870,20 @@ impl Terminal {
         )
     }
 
+    /// Pastes the clipboard and presses enter afterwards.
+    fn paste_and_run(&self) -> Action {
+        if self.read_only {
+            return Action::Run(self.focus());
+        }
+
+        Action::Run(
+            iced::clipboard::read()
+                .map(InnerMessage::PasteAndRun)
+                .map(Message)
+                .chain(self.focus()),
+        )
+    }
+
     pub fn view<'a, Theme, Renderer>(&'a self) -> iced::Element<'a, Message, Theme, Renderer>
     where
         Renderer: iced::advanced::text::Renderer<Font = iced::Font> + 'static,
@@ -289,6 +927,31 @@ impl Terminal {
             .on_scroll_done(InnerMessage::ScrollDone)
         ];
 
+        let new_lines = self.grid.new_lines_below();
+        let new_lines_hint = (new_lines > 0).then(|| {
+            let label = if new_lines == 1 {
+                "↓ 1 new line".to_string()
+            } else {
+                format!("↓ {} new lines", new_lines)
+            };
+
+            container(
+                iced::widget::button(iced::widget::text(label).size(12))
+                    .padding([2, 8])
+                    .on_press(InnerMessage::ScrollToBottom),
+            )
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .align_x(iced::alignment::Horizontal::Right)
+            .align_y(iced::alignment::Vertical::Bottom)
+            .padding(iced::Padding {
+                top: 0.0,
+                right: 24.0,
+                bottom: 8.0,
+                left: 0.0,
+            })
+        });
+
         if let Some(position) = self.context_menu_position {
             let copy_button = iced::widget::button(iced::widget::text("Copy").size(14))
                 .padding([4, 8])
@@ -329,7 +992,13 @@ impl Terminal {
                     left: position.x,
                 });
 
-            iced::widget::stack![terminal_widget, positioned_container].into()
+            let mut stack = iced::widget::stack![terminal_widget];
+            if let Some(hint) = new_lines_hint {
+                stack = stack.push(hint);
+            }
+            stack.push(positioned_container).into()
+        } else if let Some(hint) = new_lines_hint {
+            iced::widget::stack![terminal_widget, hint].into()
         } else {
             iced::widget::stack![terminal_widget].into()
         }
@@ -378,16 +1047,35 @@ struct TerminalWidget<'a> {
 struct State<R: iced::advanced::text::Renderer> {
     prerenderer: WeztermPreRenderer<R>,
     focused: bool,
+    window_focused: bool,
+    modifiers: iced::keyboard::Modifiers,
     last_cursor_blink: Instant,
     cursor_blink_currently_shown: bool,
     now: Instant,
     last_widget_width: f32,
     last_widget_height: f32,
     last_id: Option<Id>,
+    /// The style version the prerenderer uses
+    style_version: usize,
+    /// The scroll position the prerenderer last rendered, `None` after clearing its cache
+    rendered_scroll: Option<usize>,
 }
 
 const CHAR_WIDTH: f32 = 0.6;
 const CURSOR_BLINK_INTERVAL_MILLIS: u128 = 500;
+const LOCATE_CURSOR_DURATION: Duration = Duration::from_millis(400);
+/// How far the cursor flash extends beyond the cell at its start, in cells
+const LOCATE_CURSOR_SPREAD: f32 = 4.0;
+/// Opacity of typed characters which weren't echoed yet
+const PREDICTION_ALPHA: f32 = 0.6;
+/// Distance of underlines from the top of their row, relative to the text size
+const UNDERLINE_OFFSET: f32 = 1.2;
+const UNDERLINE_THICKNESS: f32 = 1.0;
+/// Height and length of a curl of curly underlines in pixels
+const CURL_AMPLITUDE: f32 = 1.5;
+const CURL_LENGTH: f32 = 6.0;
+const DASH_LENGTH: f32 = 4.0;
+const DASH_GAP: f32 = 2.0;
 
 impl<Renderer> iced::advanced::widget::operation::Focusable for State<Renderer>
 where
@@ -423,6 +1111,8 @@ where
         iced::advanced::widget::tree::State::new(State::<Renderer> {
             prerenderer: WeztermPreRenderer::new(self.term.style.clone()),
             focused: false,
+            window_focused: true,
+            modifiers: iced::keyboard::Modifiers::empty(),
             last_cursor_blink: Instant::now(),
             cursor_blink_currently_shown: false,
             now: Instant::now(),
@@ -430,6 +1120,8 @@ where
             last_id: None,
             last_widget_height: 0.0,
             last_widget_width: 0.0,
+            style_version: self.term.style_version,
+            rendered_scroll: None,
         })
     }
 
@@ -476,10 +1168,19 @@ where
 
                 if id_changed {
                     state.prerenderer.clear_cache();
+                    state.rendered_scroll = None;
                     state.last_id = Some(self.term.id.clone());
                     shell.publish(InnerMessage::IdChanged);
                 }
 
+                // colors of the cells changed, e.g. with a new palette
+                if state.style_version != self.term.style_version {
+                    state.style_version = self.term.style_version;
+                    state.prerenderer.set_style(self.term.style.clone());
+                    state.prerenderer.clear_cache();
+                    state.rendered_scroll = None;
+                }
+
                 // check if widget size has changed
                 if state.last_widget_width != widget_width
                     || state.last_widget_height != widget_height
@@ -490,10 +1191,10 @@ where
 
                     let text_size = self.term.style.text_size.unwrap_or(renderer.default_size());
                     let line_height = self.term.style.line_height.to_absolute(text_size);
-                    let char_width = text_size * CHAR_WIDTH;
+                    let char_width = self.term.style.cell_width(text_size.0);
 
                     let target_line_count = (widget_height / line_height.0) as usize;
-                    let target_col_count = (widget_width / char_width.0) as usize;
+                    let target_col_count = (widget_width / char_width) as usize;
                     let size = self.term.grid.get_size();
 
                     if size.rows != target_line_count || size.cols != target_col_count {
@@ -523,6 +1224,11 @@ where
                     state.cursor_blink_currently_shown = false;
                     shell.request_redraw();
                 }
+
+                // animate the cursor flash
+                if self.term.locate_cursor_progress().is_some() {
+                    shell.request_redraw();
+                }
             }
             iced::Event::Mouse(iced::mouse::Event::WheelScrolled { delta }) => {
                 if cursor.position_over(layout.bounds()).is_some() {
@@ -548,7 +1254,11 @@ where
                             if let Some(char_pos) =
                                 self.screen_to_visible_position(cursor_position, layout, renderer)
                             {
-                                shell.publish(InnerMessage::StartSelection(char_pos));
+                                if state.modifiers.control() {
+                                    shell.publish(InnerMessage::CtrlClick(char_pos));
+                                } else {
+                                    shell.publish(InnerMessage::StartSelection(char_pos));
+                                }
                             }
                         }
                     }
@@ -594,39 +1304,60 @@ where
                     state.unfocus();
                 }
             }
+            iced::Event::Keyboard(iced::keyboard::Event::ModifiersChanged(modifiers)) => {
+                let state = state.state.downcast_mut::<State<Renderer>>();
+                state.modifiers = *modifiers;
+            }
             iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
                 modified_key,
                 modifiers,
+                location,
                 ..
             }) => {
                 let state = state.state.downcast_mut::<State<Renderer>>();
 
                 if state.is_focused() {
-                    if let Some(filter) = &self.term.key_filter {
-                        if filter(&modified_key, &modifiers) {
-                            return;
-                        }
+                    let key_press = KeyPress {
+                        modified_key: modified_key.clone(),
+                        modifiers: *modifiers,
+                        location: *location,
+                    };
+                    if self.term.filters_key(&key_press) {
+                        return;
                     }
 
                     state.last_cursor_blink = Instant::now();
                     state.cursor_blink_currently_shown = true;
 
-                    let message = InnerMessage::KeyPress {
-                        modified_key: modified_key.clone(),
-                        modifiers: modifiers.clone(),
-                    };
-                    shell.publish(message);
+                    shell.publish(InnerMessage::KeyPress {
+                        modified_key: key_press.modified_key,
+                        modifiers: key_press.modifiers,
+                        location: key_press.location,
+                    });
 
                     shell.capture_event();
                 }
             }
             iced::Event::Window(iced::window::Event::Focused) => {
                 let state = state.state.downcast_mut::<State<Renderer>>();
+                state.window_focused = true;
                 state.focus();
                 shell.request_redraw();
             }
+            iced::Event::Window(iced::window::Event::Unfocused) => {
+                let state = state.state.downcast_mut::<State<Renderer>>();
+                state.window_focused = false;
+            }
             _ => (),
         }
+
+        // The widget state is shared between all terminals shown at this position,
+        // so the reported focus is tracked by the terminal itself.
+        let state = state.state.downcast_ref::<State<Renderer>>();
+        let focused = state.is_focused() && state.window_focused;
+        if focused != self.term.grid.is_focused() {
+            shell.publish(InnerMessage::FocusChanged(focused));
+        }
     }
 
     fn layout(
@@ -637,7 +1368,12 @@ where
     ) -> iced::advanced::layout::Node {
         let state = tree.state.downcast_mut::<State<Renderer>>();
 
-        state.prerenderer.update(&self.term.grid, renderer);
+        // a frozen terminal only renders again once it is scrolled
+        let scroll = self.term.grid.get_scroll();
+        if !self.term.frozen || state.rendered_scroll != Some(scroll) {
+            state.prerenderer.update(&self.term.grid, renderer);
+            state.rendered_scroll = Some(scroll);
+        }
 
         iced::advanced::layout::Node::new(limits.max())
     }
@@ -661,13 +1397,15 @@ where
             iced::Vector::new(self.term.style.padding.left, self.term.style.padding.top);
         let translation = layout.position() - iced::Point::ORIGIN + padding_offset;
 
+        let (background_color, foreground_color) = self.term.default_colors();
+
         // terminal Background
         renderer.fill_quad(
             iced::advanced::renderer::Quad {
                 bounds: layout.bounds(),
                 ..Default::default()
             },
-            self.term.style.background_color,
+            background_color,
         );
 
         let size = self
@@ -680,11 +1418,24 @@ where
 
         // drawing text background
         for (row_index, render_data) in state.prerenderer.visible_rows().enumerate() {
-            let Some((paragraph, spans)) = render_data else {
+            let Some((paragraph, spans, underlines)) = render_data else {
                 continue;
             };
             let y_offset = y_multiplier * row_index as f32;
 
+            if self.term.style.letter_spacing != 0.0 {
+                self.draw_spaced_row(
+                    renderer,
+                    spans,
+                    underlines,
+                    iced::Point::ORIGIN + translation + iced::Vector::new(0.0, y_offset),
+                    size,
+                    y_multiplier,
+                    bounds,
+                );
+                continue;
+            }
+
             for (index, span) in spans.iter().enumerate() {
                 if let Some(highlight) = span.highlight {
                     let regions = paragraph.span_bounds(index);
@@ -713,12 +1464,28 @@ where
             renderer.fill_paragraph(
                 &paragraph,
                 bounds.position() + padding_offset + iced::Vector::new(0.0, y_offset),
-                self.term.style.foreground_color,
+                foreground_color,
                 bounds,
             );
+
+            for underline in underlines {
+                let color = underline
+                    .color
+                    .or(spans[underline.span].color)
+                    .unwrap_or(foreground_color);
+
+                for region in paragraph.span_bounds(underline.span) {
+                    let position = region.position()
+                        + translation
+                        + iced::Vector::new(0.0, y_offset + size.0 * UNDERLINE_OFFSET);
+                    draw_underline(renderer, underline.style, position, region.width, color);
+                }
+            }
         }
 
-        self.draw_cursor(renderer, &state, translation);
+        self.draw_predictions(renderer, translation, bounds);
+        self.draw_cursor(renderer, &state, translation, bounds);
+        self.draw_cursor_flash(renderer, translation, bounds);
     }
 }
 
@@ -755,8 +1522,7 @@ impl<'a> TerminalWidget<'a> {
             .text_size
             .unwrap_or_else(|| renderer.default_size());
         let line_height = self.term.style.line_height.to_absolute(text_size).0;
-        let text_size = text_size.0;
-        let char_width = text_size * CHAR_WIDTH;
+        let char_width = self.term.style.cell_width(text_size.0);
 
         // Convert to character coordinates
         let char_x = (relative_pos.x / char_width) as usize;
@@ -771,13 +1537,89 @@ impl<'a> TerminalWidget<'a> {
         })
     }
 
+    /// Draws a row cell by cell.
+    /// Paragraphs are always laid out with the natural advance of the font,
+    /// so this is required to honor the letter spacing.
+    fn draw_spaced_row<Renderer>(
+        &self,
+        renderer: &mut Renderer,
+        spans: &[iced::advanced::text::Span<'_, (), iced::Font>],
+        underlines: &[SpanUnderline],
+        row_position: iced::Point,
+        text_size: iced::Pixels,
+        line_height: f32,
+        clip_bounds: Rectangle,
+    ) where
+        Renderer: iced::advanced::text::Renderer<Font = iced::Font>,
+    {
+        let cell_width = self.term.style.cell_width(text_size.0);
+        let mut column = 0;
+
+        for (index, span) in spans.iter().enumerate() {
+            let cell_count = span.text.chars().count();
+            let span_position = row_position + Vector::new(column as f32 * cell_width, 0.0);
+            let span_size = Size::new(cell_count as f32 * cell_width, line_height);
+
+            if let Some(highlight) = span.highlight {
+                renderer.fill_quad(
+                    iced::advanced::renderer::Quad {
+                        bounds: Rectangle::new(span_position, span_size),
+                        border: highlight.border,
+                        ..Default::default()
+                    },
+                    highlight.background,
+                );
+            }
+
+            let color = span.color.unwrap_or(self.term.default_colors().1);
+
+            for (offset, character) in span.text.chars().enumerate() {
+                let position = span_position + Vector::new(offset as f32 * cell_width, 0.0);
+
+                renderer.fill_text(
+                    iced::advanced::Text {
+                        content: character.to_string(),
+                        bounds: Size::new(f32::INFINITY, line_height),
+                        size: text_size,
+                        line_height: iced::advanced::text::LineHeight::default(),
+                        font: span.font.unwrap_or(self.term.style.font),
+                        align_x: iced::advanced::text::Alignment::Left,
+                        align_y: iced::alignment::Vertical::Top,
+                        shaping: iced::advanced::text::Shaping::Auto,
+                        wrapping: iced::widget::text::Wrapping::None,
+                        hint_factor: None,
+                    },
+                    position,
+                    color,
+                    clip_bounds,
+                );
+            }
+
+            for underline in underlines
+                .iter()
+                .filter(|underline| underline.span == index)
+            {
+                draw_underline(
+                    renderer,
+                    underline.style,
+                    span_position + Vector::new(0.0, text_size.0 * UNDERLINE_OFFSET),
+                    span_size.width,
+                    underline.color.unwrap_or(color),
+                );
+            }
+
+            column += cell_count;
+        }
+    }
+
     fn draw_cursor<Renderer>(
         &self,
         renderer: &mut Renderer,
         state: &State<Renderer>,
         translation: iced::Vector,
+        clip_bounds: Rectangle,
     ) where
-        Renderer: iced::advanced::text::Renderer,
+        Renderer: iced::advanced::text::Renderer<Font = iced::Font>,
     {
         let Some(cursor) = self.term.grid.get_cursor() else {
             return;
@@ -800,8 +1642,7 @@ impl<'a> TerminalWidget<'a> {
             .unwrap_or_else(|| renderer.default_size());
 
         let line_height = self.term.style.line_height.to_absolute(text_size).0;
-        let text_size = text_size.0;
-        let char_width = text_size * CHAR_WIDTH;
+        let char_width = self.term.style.cell_width(text_size.0);
 
         let base_cursor_position = iced::Point::new(
             cursor.x as f32 * char_width,
@@ -830,13 +1671,375 @@ impl<'a> TerminalWidget<'a> {
             ),
         };
 
+        // applications may override the cursor color via OSC 12
+        let cursor_color = self
+            .term
+            .grid
+            .cursor_color()
+            .unwrap_or(self.term.style.cursor_color);
+
         renderer.fill_quad(
             iced::advanced::renderer::Quad {
                 bounds: cursor_bounds,
                 border: iced::Border::default(),
                 ..Default::default()
             },
-            iced::Color::WHITE,
+            cursor_color,
+        );
+
+        // redraw the character below a block cursor, so it stays readable
+        if let CursorShape::Block = self.term.style.cursor_shape
+            && let Some(character) = self.term.grid.visible_cell(&cursor)
+            && !character.trim().is_empty()
+        {
+            renderer.fill_text(
+                iced::advanced::Text {
+                    content: character,
+                    bounds: iced::Size::new(f32::INFINITY, line_height),
+                    size: text_size,
+                    line_height: iced::advanced::text::LineHeight::default(),
+                    font: self.term.style.font,
+                    align_x: iced::advanced::text::Alignment::Left,
+                    align_y: iced::alignment::Vertical::Top,
+                    shaping: iced::advanced::text::Shaping::Auto,
+                    wrapping: iced::widget::text::Wrapping::None,
+                    hint_factor: None,
+                },
+                base_cursor_position + translation,
+                self.term
+                    .style
+                    .cursor_text_color
+                    .unwrap_or(self.term.default_colors().0),
+                clip_bounds,
+            );
+        }
+    }
+
+    /// Typed characters which weren't echoed yet, dimmed and underlined from the cursor on
+    fn draw_predictions<Renderer>(
+        &self,
+        renderer: &mut Renderer,
+        translation: iced::Vector,
+        clip_bounds: Rectangle,
+    ) where
+        Renderer: iced::advanced::text::Renderer<Font = iced::Font>,
+    {
+        if !self.term.echo_confirmed || self.term.predicted.is_empty() {
+            return;
+        }
+        let Some(cursor) = self.term.grid.get_cursor() else {
+            return;
+        };
+
+        let text_size = self
+            .term
+            .style
+            .text_size
+            .unwrap_or_else(|| renderer.default_size());
+        let line_height = self.term.style.line_height.to_absolute(text_size).0;
+        let char_width = self.term.style.cell_width(text_size.0);
+
+        // only the rest of the line, the wrapping is left to the process
+        let columns = self.term.grid.get_size().cols.saturating_sub(cursor.x);
+        let (background, foreground) = self.term.default_colors();
+        let foreground = foreground.scale_alpha(PREDICTION_ALPHA);
+
+        for (offset, character) in self.term.predicted.chars().take(columns).enumerate() {
+            let position = iced::Point::new(
+                (cursor.x + offset) as f32 * char_width,
+                cursor.y as f32 * line_height,
+            ) + translation;
+
+            renderer.fill_quad(
+                iced::advanced::renderer::Quad {
+                    bounds: Rectangle::new(position, Size::new(char_width, line_height)),
+                    ..Default::default()
+                },
+                background,
+            );
+            renderer.fill_text(
+                iced::advanced::Text {
+                    content: character.to_string(),
+                    bounds: iced::Size::new(f32::INFINITY, line_height),
+                    size: text_size,
+                    line_height: iced::advanced::text::LineHeight::default(),
+                    font: self.term.style.font,
+                    align_x: iced::advanced::text::Alignment::Left,
+                    align_y: iced::alignment::Vertical::Top,
+                    shaping: iced::advanced::text::Shaping::Auto,
+                    wrapping: iced::widget::text::Wrapping::None,
+                    hint_factor: None,
+                },
+                position,
+                foreground,
+                clip_bounds,
+            );
+            renderer.fill_quad(
+                iced::advanced::renderer::Quad {
+                    bounds: Rectangle::new(
+                        position + iced::Vector::new(0.0, line_height - 1.0),
+                        Size::new(char_width, 1.0),
+                    ),
+                    ..Default::default()
+                },
+                foreground,
+            );
+        }
+    }
+
+    /// A ring shrinking onto the cursor cell while fading out
+    fn draw_cursor_flash<Renderer>(
+        &self,
+        renderer: &mut Renderer,
+        translation: iced::Vector,
+        clip_bounds: Rectangle,
+    ) where
+        Renderer: iced::advanced::text::Renderer<Font = iced::Font>,
+    {
+        let Some(progress) = self.term.locate_cursor_progress() else {
+            return;
+        };
+        let Some(cursor) = self.term.grid.get_cursor() else {
+            return;
+        };
+
+        let text_size = self
+            .term
+            .style
+            .text_size
+            .unwrap_or_else(|| renderer.default_size());
+        let line_height = self.term.style.line_height.to_absolute(text_size).0;
+        let char_width = self.term.style.cell_width(text_size.0);
+
+        let spread = (1.0 - progress) * LOCATE_CURSOR_SPREAD * char_width;
+        let cell = Rectangle::new(
+            iced::Point::new(cursor.x as f32 * char_width, cursor.y as f32 * line_height)
+                + translation,
+            Size::new(char_width, line_height),
+        );
+        let ring = cell.expand(spread);
+
+        let cursor_color = self
+            .term
+            .grid
+            .cursor_color()
+            .unwrap_or(self.term.style.cursor_color);
+
+        renderer.with_layer(clip_bounds, |renderer| {
+            renderer.fill_quad(
+                iced::advanced::renderer::Quad {
+                    bounds: ring,
+                    border: iced::Border {
+                        color: cursor_color.scale_alpha(1.0 - progress),
+                        width: 2.0,
+                        radius: (spread + 2.0).into(),
+                    },
+                    ..Default::default()
+                },
+                iced::Color::TRANSPARENT,
+            );
+        });
+    }
+}
+
+fn is_character(key: &iced::keyboard::Key, character: &str) -> bool {
+    matches!(key, iced::keyboard::Key::Character(c) if c.eq_ignore_ascii_case(character))
+}
+
+/// Removes CSI, OSC and other escape sequences, keeping the printed text
+fn strip_escape_sequences(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != '\x1b' {
+            stripped.push(char);
+            continue;
+        }
+
+        match chars.next() {
+            // parameters and intermediates up to the final byte
+            Some('[') => {
+                while chars
+                    .next()
+                    .is_some_and(|char| !('@'..='~').contains(&char))
+                {}
+            }
+            // terminated by BEL or ST
+            Some(']') => {
+                while let Some(char) = chars.next() {
+                    if char == '\x07' || (char == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // charset designations and the like, an intermediate and a final byte
+            Some(' '..='/') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Whether the text is nothing but a single web URL
+fn is_url(text: &str) -> bool {
+    let text = text.trim();
+    (text.starts_with("https://") || text.starts_with("http://"))
+        && !text.contains(char::is_whitespace)
+}
+
+/// Draws an underline of the given width, starting at the position
+fn draw_underline<Renderer>(
+    renderer: &mut Renderer,
+    style: UnderlineStyle,
+    position: iced::Point,
+    width: f32,
+    color: iced::Color,
+) where
+    Renderer: iced::advanced::Renderer,
+{
+    let mut line = |x: f32, y: f32, width: f32| {
+        renderer.fill_quad(
+            iced::advanced::renderer::Quad {
+                bounds: Rectangle::new(
+                    iced::Point::new(x, y),
+                    Size::new(width, UNDERLINE_THICKNESS),
+                ),
+                ..Default::default()
+            },
+            color,
+        );
+    };
+    let end = position.x + width;
+
+    match style {
+        UnderlineStyle::Single => line(position.x, position.y, width),
+        UnderlineStyle::Double => {
+            line(position.x, position.y - UNDERLINE_THICKNESS, width);
+            line(position.x, position.y + UNDERLINE_THICKNESS, width);
+        }
+        UnderlineStyle::Curly => {
+            // pixel by pixel, the phase depends on x so adjacent spans continue the wave
+            let mut x = position.x.floor();
+            while x < end {
+                let phase = x / CURL_LENGTH * std::f32::consts::TAU;
+                line(x, position.y + CURL_AMPLITUDE * phase.sin(), 1.0);
+                x += 1.0;
+            }
+        }
+        UnderlineStyle::Dotted => {
+            let mut x = position.x;
+            while x < end {
+                line(x, position.y, UNDERLINE_THICKNESS.min(end - x));
+                x += 2.0 * UNDERLINE_THICKNESS;
+            }
+        }
+        UnderlineStyle::Dashed => {
+            let mut x = position.x;
+            while x < end {
+                line(x, position.y, DASH_LENGTH.min(end - x));
+                x += DASH_LENGTH + DASH_GAP;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> Terminal {
+        let (mut terminal, _) = Terminal::new();
+        terminal.set_local_echo(true);
+        for char in text.chars() {
+            terminal.predict(
+                &iced::keyboard::Key::Character(char.to_string().into()),
+                iced::keyboard::Modifiers::empty(),
+            );
+        }
+        terminal
+    }
+
+    #[test]
+    fn echo_with_escape_sequences() {
+        let mut terminal = typed("ls");
+        terminal.reconcile_predictions(b"\x1b[32ml\x1b]0;title\x07s\x1b[0m");
+        assert_eq!(terminal.predicted, "");
+        assert!(terminal.echo_confirmed);
+    }
+
+    #[test]
+    fn partial_echo() {
+        let mut terminal = typed("abc");
+        terminal.reconcile_predictions(b"a");
+        assert_eq!(terminal.predicted, "bc");
+
+        // output without text, e.g. a title update, keeps the prediction
+        terminal.reconcile_predictions(b"\x1b]2;title\x1b\\");
+        assert_eq!(terminal.predicted, "bc");
+    }
+
+    #[test]
+    fn misprediction() {
+        let mut terminal = typed("ls");
+        terminal.reconcile_predictions(b"xyz");
+        assert_eq!(terminal.predicted, "");
+        assert!(!terminal.echo_confirmed);
+
+        // the characters appear in the output, but not at the cursor
+        let mut terminal = typed("ls");
+        terminal.reconcile_predictions(b"total 0\r\nls");
+        assert_eq!(terminal.predicted, "");
+    }
+
+    #[test]
+    fn strips_escape_sequences() {
+        assert_eq!(
+            strip_escape_sequences("\x1b(Ba\x1b[1;31mb\x1b]8;;https://example.com\x1b\\c\x1b="),
+            "abc"
+        );
+    }
+
+    #[test]
+    fn reverse_video_swaps_the_default_colors() {
+        let (mut terminal, _) = Terminal::new();
+        let (background, foreground) = terminal.default_colors();
+
+        terminal.advance_bytes(b"\x1b[?5h");
+        let (reversed_background, reversed_foreground) = terminal.default_colors();
+        assert_eq!(
+            reversed_background,
+            iced::Color {
+                a: background.a,
+                ..foreground
+            }
         );
+        assert_eq!(
+            reversed_foreground,
+            iced::Color {
+                a: 1.0,
+                ..background
+            }
+        );
+
+        terminal.advance_bytes(b"\x1b[?5l");
+        assert_eq!(terminal.default_colors(), (background, foreground));
+    }
+
+    #[test]
+    fn paste_and_run_intercepts_urls() {
+        let (mut terminal, _) = Terminal::new();
+        terminal.set_intercept_url_paste(true);
+
+        let url = Some(" https://example.com\n".to_string());
+        assert!(matches!(
+            terminal.update(Message(InnerMessage::PasteAndRun(url))),
+            Action::UrlPasted(url) if url == "https://example.com"
+        ));
+        assert!(matches!(
+            terminal.update(Message(InnerMessage::PasteAndRun(Some("ls".to_string())))),
+            Action::Input(_)
+        ));
     }
 }
@@ -5,7 +5,7 @@ use wezterm_term::color::ColorPalette;
 
 use iced::{Padding, Pixels};
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Style {
     pub line_height: iced::widget::text::LineHeight,
     pub text_size: Option<Pixels>,
@@ -14,15 +14,27 @@ pub struct Style {
     pub foreground_color: iced::Color,
     pub font: iced::Font,
     pub cursor_shape: CursorShape,
+    pub cursor_color: iced::Color,
+    /// Color of the character below a block cursor, defaults to the background color.
+    pub cursor_text_color: Option<iced::Color>,
+    /// Additional horizontal space between cells in pixels, can be negative.
+    pub letter_spacing: f32,
+    /// Bold text in one of the 8 basic colors is drawn in the bright variant of the color
+    /// instead of a bold font. Any other bold text is drawn with a bold font either way.
+    pub bold_is_bright: bool,
+    /// Contrast ratio the text keeps to its background, from `1.0` (off) to `21.0` (black on white).
+    /// Text below it is lightened or darkened.
+    pub minimum_contrast: f32,
     /// This value is used to set the height of the background for the text.
     /// If you use a custom font, you might have to experiment which value works best for your font.
     // pub font_height_modifier: f32,
     pub palette: Arc<Palette256>,
 }
 
+#[derive(PartialEq)]
 pub struct Palette256(pub [iced::Color; 256]);
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CursorShape {
     Block,
     Underline,
@@ -56,6 +68,11 @@ const DEFAULT_STYLE: LazyCell<Style> = LazyCell::new(|| {
         text_size: None,
         padding: Padding::new(10.0),
         cursor_shape: CursorShape::Underline,
+        cursor_color: iced::Color::WHITE,
+        cursor_text_color: None,
+        letter_spacing: 0.0,
+        bold_is_bright: true,
+        minimum_contrast: 1.0,
         background_color,
         foreground_color,
         font: iced::Font::MONOSPACE,
@@ -111,6 +128,38 @@ impl Style {
         self
     }
 
+    pub fn cursor_color(mut self, color: impl Into<iced::Color>) -> Self {
+        self.cursor_color = color.into();
+        self
+    }
+
+    pub fn cursor_text_color(mut self, color: impl Into<iced::Color>) -> Self {
+        self.cursor_text_color = Some(color.into());
+        self
+    }
+
+    pub fn letter_spacing(mut self, letter_spacing: f32) -> Self {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    pub fn bold_is_bright(mut self, bold_is_bright: bool) -> Self {
+        self.bold_is_bright = bold_is_bright;
+        self
+    }
+
+    pub fn minimum_contrast(mut self, minimum_contrast: f32) -> Self {
+        self.minimum_contrast = minimum_contrast;
+        self
+    }
+
+    /// Width of a single cell, including the letter spacing.
+    /// Negative spacing can shrink a cell down to half its natural width.
+    pub(crate) fn cell_width(&self, text_size: f32) -> f32 {
+        let natural_width = text_size * super::CHAR_WIDTH;
+        (natural_width + self.letter_spacing).max(natural_width * 0.5)
+    }
+
     pub(crate) fn get_color(&self, color: ColorAttribute) -> Option<iced::Color> {
         match color {
             ColorAttribute::TrueColorWithPaletteFallback(srgba_tuple, _)
@@ -3,28 +3,66 @@ use iced::advanced::text::{self};
 pub trait TerminalGrid {
     fn advance_bytes(&mut self, bytes: &[u8]);
     fn resize(&mut self, size: Size);
+    /// Full reset (RIS), also erasing the scrollback
+    fn reset(&mut self);
 
     fn press_key(
         &mut self,
         key: iced::keyboard::Key,
         modifiers: iced::keyboard::Modifiers,
+        location: iced::keyboard::Location,
     ) -> Option<Vec<u8>>;
     fn paste(&mut self, text: &str) -> Option<Vec<u8>>;
+    /// Sends raw input, keeping the order with keypresses
+    fn send_input(&mut self, input: Vec<u8>);
 
     fn scroll(&mut self, lines: isize);
     fn scroll_to(&mut self, y: usize);
+    fn scroll_to_bottom(&mut self);
+    /// Lines of output which arrived while scrolled up, reset once scrolled back to the bottom
+    fn new_lines_below(&self) -> usize;
+    /// Scrolls the previous or next prompt marked via OSC 133 to the top.
+    /// Returns `false` if no prompts were marked.
+    fn jump_to_prompt(&mut self, previous: bool) -> bool;
+    /// Scrolls the prompt of the last command marked via OSC 133 to the top,
+    /// showing the command along with its output.
+    /// Returns `false` if no prompts were marked.
+    fn scroll_to_last_prompt(&mut self) -> bool;
     fn get_scroll(&self) -> usize;
     fn available_lines(&self) -> usize;
+    /// While frozen, the view stays in place instead of following the output.
+    /// Unfreezing returns to the bottom if the output was followed before.
+    fn set_frozen(&mut self, frozen: bool);
 
     fn start_selection(&mut self, start: VisiblePosition);
     fn move_selection(&mut self, end: VisiblePosition);
     fn end_selection(&mut self);
+    /// Selects the output of the last command marked via OSC 133.
+    /// Returns `false` if there is none.
+    fn select_last_output(&mut self) -> bool;
     fn currently_selecting(&self) -> bool;
     fn selected_text(&self) -> Option<String>;
 
+    fn set_focused(&mut self, focused: bool);
+    fn is_focused(&self) -> bool;
+
     fn get_title(&self) -> &str;
+    /// Whether the application has set a title
+    fn has_title(&self) -> bool;
     fn get_size(&self) -> Size;
     fn get_cursor(&self) -> Option<VisiblePosition>;
+    /// The cursor color set by the application, if any
+    fn cursor_color(&self) -> Option<iced::Color>;
+    fn is_alt_screen(&self) -> bool;
+    /// Whether the default colors are swapped (DECSCNM)
+    fn reverse_video(&self) -> bool;
+    fn visible_cell(&self, position: &VisiblePosition) -> Option<String>;
+    /// The text of the visible row at the position,
+    /// along with the index of the character at the position in it
+    fn visible_line(&self, position: &VisiblePosition) -> Option<(String, usize)>;
+
+    fn screen_contents(&self) -> String;
+    fn cell_at(&self, row: usize, col: usize) -> Option<String>;
 }
 
 pub trait PreRenderer<R>
@@ -38,7 +76,33 @@ where
     fn update(&mut self, grid: &Self::Grid, renderer: &R);
     fn visible_rows<'a>(
         &'a self,
-    ) -> impl Iterator<Item = Option<(&'a R::Paragraph, &'a [text::Span<'a, (), R::Font>])>>;
+    ) -> impl Iterator<
+        Item = Option<(
+            &'a R::Paragraph,
+            &'a [text::Span<'a, (), R::Font>],
+            &'a [SpanUnderline],
+        )>,
+    >;
+}
+
+/// An underline drawn by the widget itself, paragraphs only support plain ones in the text color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpanUnderline {
+    /// Index of the underlined span in its row
+    pub span: usize,
+    pub style: UnderlineStyle,
+    /// Set via SGR 58, defaults to the color of the text
+    pub color: Option<iced::Color>,
+}
+
+/// Set via SGR 4 and its sub-parameters, e.g. `4:3` for curly underlines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnderlineStyle {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
 }
 
 #[derive(Debug, Clone, Copy)]
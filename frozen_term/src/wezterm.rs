@@ -1,25 +1,32 @@
 use std::{ops::Range, sync::Arc};
 
-use termwiz::surface::CursorVisibility;
+use termwiz::{color::SrgbaTuple, surface::CursorVisibility};
 use tokio::sync::mpsc;
-use tokio_stream::Stream;
+use tokio_stream::{Stream, StreamExt};
 use wezterm_term::{PhysRowIndex, TerminalConfiguration, TerminalSize, color::ColorPalette};
 
 use crate::{
     terminal_grid::{Size, TerminalGrid, VisiblePosition},
-    wezterm::selection::{SelectionPosition, SelectionState, is_selected},
+    wezterm::{
+        selection::{SelectionPosition, SelectionState, is_selected},
+        sequences::{Sequence, SequenceScanner},
+    },
 };
 
 pub mod prerenderer;
 pub mod selection;
+pub mod sequences;
+#[cfg(feature = "testing")]
+pub mod snapshot;
 
+/// Unbounded, as wezterm writes from within the parser, where neither blocking nor dropping a reply is an option
 pub struct BridgedWriter {
-    send: mpsc::Sender<Vec<u8>>,
+    send: mpsc::UnboundedSender<Vec<u8>>,
 }
 
 impl std::io::Write for BridgedWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        if self.send.blocking_send(buf.to_vec()).is_ok() {
+        if self.send.send(buf.to_vec()).is_ok() {
             Ok(buf.len())
         } else {
             Ok(0)
@@ -31,13 +38,74 @@ impl std::io::Write for BridgedWriter {
     }
 }
 
+/// Forwards clipboard writes requested via OSC 52
 #[derive(Debug)]
-pub struct Config {}
+struct BridgedClipboard {
+    send: mpsc::Sender<String>,
+}
+
+impl wezterm_term::Clipboard for BridgedClipboard {
+    fn set_contents(
+        &self,
+        selection: wezterm_term::ClipboardSelection,
+        data: Option<String>,
+    ) -> anyhow::Result<()> {
+        // iced has no access to the primary selection
+        if matches!(selection, wezterm_term::ClipboardSelection::Clipboard)
+            && let Some(data) = data
+        {
+            let _ = self.send.try_send(data);
+        }
+        Ok(())
+    }
+}
+
+/// Forwards the bell (BEL)
+struct BridgedAlerts {
+    send: mpsc::Sender<()>,
+}
+
+impl wezterm_term::AlertHandler for BridgedAlerts {
+    fn alert(&mut self, alert: wezterm_term::Alert) {
+        if let wezterm_term::Alert::Bell = alert {
+            // a burst of bells only has to be reported once
+            let _ = self.send.try_send(());
+        }
+    }
+}
+
+/// Events the grid emits on its own
+pub enum GridEvent {
+    /// Input for the process, e.g. from keypresses or answers to queries
+    Input(Vec<u8>),
+    /// The application asked to set the clipboard via OSC 52
+    Clipboard(String),
+    /// The application rang the bell
+    Bell,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    scrollback_size: usize,
+}
+
+/// Lines kept in the scrollback of the primary screen, unless changed via [`WeztermGrid::set_scrollback_size`].
+/// The alternate screen never has a scrollback, it only ever holds the visible rows.
+pub const DEFAULT_SCROLLBACK_SIZE: usize = 3500;
+/// Titles kept by `CSI 22 t`, the oldest one is dropped when pushing more
+const MAX_TITLE_STACK: usize = 10;
+/// The reply to primary device attributes (DA1): a VT520-class terminal with
+/// selective erase, windowing and ANSI color. Unlike wezterm's own reply, sixel isn't advertised.
+const PRIMARY_DEVICE_ATTRIBUTES: &[u8] = b"\x1b[?65;6;18;22c";
 
 impl TerminalConfiguration for Config {
     fn color_palette(&self) -> wezterm_term::color::ColorPalette {
         ColorPalette::default()
     }
+
+    fn scrollback_size(&self) -> usize {
+        self.scrollback_size
+    }
 }
 
 pub struct WeztermGrid {
@@ -45,35 +113,80 @@ pub struct WeztermGrid {
     scroll_offset: usize,
     size: Size,
     selection: SelectionState,
+    focused: bool,
+    default_cursor_color: SrgbaTuple,
+    /// Lines of output which arrived while scrolled up
+    new_lines_below: usize,
+    /// Set while frozen, to whether the output was followed before
+    frozen: Option<bool>,
+    /// Raw input which has to stay in order with the input generated by wezterm
+    input: mpsc::UnboundedSender<Vec<u8>>,
+    /// Set by DECKPAM (`ESC =`), reset by DECKPNM (`ESC >`), wezterm doesn't expose it
+    application_keypad: bool,
+    sequences: SequenceScanner,
+    /// Window titles saved by `CSI 22 t` and restored by `CSI 23 t`
+    title_stack: Vec<String>,
+    /// The title wezterm reports before the application set one
+    initial_title: String,
 }
 
 impl WeztermGrid {
-    pub fn new() -> (Self, impl Stream<Item = Vec<u8>>) {
+    pub fn new() -> (Self, impl Stream<Item = GridEvent>) {
         let term_size = wezterm_term::TerminalSize::default();
         let size = Size {
             rows: term_size.rows,
             cols: term_size.cols,
         };
 
-        let (send, recv) = mpsc::channel(100);
-        let recv = tokio_stream::wrappers::ReceiverStream::new(recv);
+        let (send, recv) = mpsc::unbounded_channel();
+        let recv = tokio_stream::wrappers::UnboundedReceiverStream::new(recv);
 
-        let term = wezterm_term::Terminal::new(
+        let (clipboard_send, clipboard_recv) = mpsc::channel(4);
+        let clipboard_recv = tokio_stream::wrappers::ReceiverStream::new(clipboard_recv);
+
+        let mut term = wezterm_term::Terminal::new(
             term_size,
-            Arc::new(Config {}),
+            Arc::new(Config {
+                scrollback_size: DEFAULT_SCROLLBACK_SIZE,
+            }),
             "frozen_term",
             env!("CARGO_PKG_VERSION"),
-            Box::new(BridgedWriter { send }),
+            Box::new(BridgedWriter { send: send.clone() }),
+        );
+        term.set_clipboard(
+            &(Arc::new(BridgedClipboard {
+                send: clipboard_send,
+            }) as Arc<dyn wezterm_term::Clipboard>),
         );
 
+        let (bell_send, bell_recv) = mpsc::channel(1);
+        let bell_recv = tokio_stream::wrappers::ReceiverStream::new(bell_recv);
+        term.set_notification_handler(Box::new(BridgedAlerts { send: bell_send }));
+
+        let initial_title = term.get_title().to_string();
+
+        let events = recv
+            .map(GridEvent::Input)
+            .merge(clipboard_recv.map(GridEvent::Clipboard))
+            .merge(bell_recv.map(|()| GridEvent::Bell));
+
         (
             Self {
                 terminal: term,
                 scroll_offset: 0,
                 selection: SelectionState::new(),
                 size,
+                focused: false,
+                default_cursor_color: ColorPalette::default().cursor_bg,
+                new_lines_below: 0,
+                frozen: None,
+                input: send,
+                application_keypad: false,
+                sequences: SequenceScanner::default(),
+                title_stack: Vec::new(),
+                initial_title,
             },
-            recv,
+            events,
         )
     }
 
@@ -112,11 +225,113 @@ impl WeztermGrid {
 
     fn update_scroll(&mut self, new_offset: usize) {
         self.scroll_offset = new_offset.min(self.max_scroll()).max(self.min_scroll());
+        if self.scroll_offset == self.max_scroll() {
+            self.new_lines_below = 0;
+        }
         if let Some(invalidate) = self.selection.set_scroll(self.scroll_offset) {
             self.invalidate_lines(invalidate);
         }
     }
 
+    /// The stable row range of the screen, ignoring the current scroll position.
+    fn screen_range(&self) -> Range<usize> {
+        let start = self.max_scroll();
+        start..start + self.terminal.screen().physical_rows
+    }
+
+    /// Feeds output to wezterm, handling the sequences it ignores in between
+    fn advance_terminal(&mut self, bytes: &[u8]) {
+        let mut start = 0;
+        for (end, sequence) in self.sequences.scan(bytes) {
+            if is_primary_device_attributes(&sequence) {
+                // CAN instead of the final byte aborts the query, wezterm's reply would advertise sixel
+                self.terminal.advance_bytes(&bytes[start..end - 1]);
+                self.terminal.advance_bytes(b"\x18");
+            } else {
+                self.terminal.advance_bytes(&bytes[start..end]);
+            }
+            start = end;
+            self.handle_sequence(sequence);
+        }
+        self.terminal.advance_bytes(&bytes[start..]);
+    }
+
+    fn handle_sequence(&mut self, sequence: Sequence) {
+        match sequence {
+            Sequence::Csi {
+                params,
+                action: 't',
+            } => {
+                let mut params = params.split(';');
+                let operation = params.next();
+                // only the window title is tracked, not the icon title
+                if !matches!(params.next().unwrap_or_default(), "" | "0" | "2") {
+                    return;
+                }
+
+                match operation {
+                    Some("22") => {
+                        if self.title_stack.len() >= MAX_TITLE_STACK {
+                            self.title_stack.remove(0);
+                        }
+                        self.title_stack.push(self.terminal.get_title().to_string());
+                    }
+                    Some("23") => {
+                        if let Some(title) = self.title_stack.pop() {
+                            // there is no way to set the title directly
+                            self.terminal
+                                .advance_bytes(format!("\x1b]2;{}\x1b\\", title));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // ED 3, wezterm already erased the scrollback
+            Sequence::Csi {
+                params,
+                action: 'J',
+            } if params == "3" => {
+                if let Some(invalidate) = self.selection.clear() {
+                    self.invalidate_lines(invalidate);
+                }
+                self.new_lines_below = 0;
+            }
+            sequence if is_primary_device_attributes(&sequence) => {
+                let _ = self.input.send(PRIMARY_DEVICE_ATTRIBUTES.to_vec());
+            }
+            Sequence::Esc('=') => self.application_keypad = true,
+            // DECKPNM and RIS
+            Sequence::Esc('>' | 'c') => self.application_keypad = false,
+            Sequence::Csi { .. } | Sequence::Esc(_) => {}
+        }
+    }
+
+    /// The working directory the shell reported via OSC 7
+    pub fn current_dir(&self) -> Option<std::path::PathBuf> {
+        self.terminal.get_current_dir()?.to_file_path().ok()
+    }
+
+    /// Lines beyond the limit are dropped once the output scrolls further.
+    pub fn set_scrollback_size(&mut self, lines: usize) {
+        self.terminal.set_config(Arc::new(Config {
+            scrollback_size: lines,
+        }));
+    }
+
+    /// Stable rows of the prompts the shell marked via OSC 133, oldest first
+    fn prompt_rows(&mut self) -> Vec<usize> {
+        let Ok(zones) = self.terminal.get_semantic_zones() else {
+            return Vec::new();
+        };
+        let mut rows: Vec<usize> = zones
+            .iter()
+            .filter(|zone| zone.semantic_type == wezterm_term::SemanticType::Prompt)
+            .map(|zone| zone.start_y.max(0) as usize)
+            .collect();
+        rows.dedup();
+        rows
+    }
+
     fn screen_lines(&self, range: Range<usize>) -> Vec<wezterm_term::Line> {
         let screen = self.terminal.screen();
         let range = screen.stable_range(&(range.start as isize..range.end as isize));
@@ -126,39 +341,64 @@ impl WeztermGrid {
 
 impl TerminalGrid for WeztermGrid {
     fn advance_bytes(&mut self, bytes: &[u8]) {
-        let auto_scroll = self.scroll_offset == self.max_scroll();
-        self.terminal.advance_bytes(bytes);
-        if auto_scroll {
+        let previous_max_scroll = self.max_scroll();
+        let auto_scroll = self.frozen.is_none() && self.scroll_offset == previous_max_scroll;
+        let was_alt_screen = self.terminal.is_alt_screen_active();
+        self.advance_terminal(bytes);
+        // Both screens have their own row indices, so an offset into one is meaningless for the other.
+        let screen_switched = was_alt_screen != self.terminal.is_alt_screen_active();
+        if auto_scroll || screen_switched {
             self.update_scroll(self.max_scroll());
         } else {
+            self.new_lines_below += self.max_scroll().saturating_sub(previous_max_scroll);
             self.update_scroll(self.scroll_offset);
         }
     }
 
-    fn resize(&mut self, size: Size) {
-        let diff = self.size.rows.abs_diff(size.rows);
-
-        let new_scroll = if size.rows > self.size.rows {
-            self.scroll_offset - diff
-        } else {
-            self.scroll_offset + diff
-        };
+    fn reset(&mut self) {
+        self.title_stack.clear();
+        // wezterm resets the modes, attributes, charsets and margins, and returns to the primary screen.
+        // ED 3 takes care of the scrollback and the selection.
+        self.advance_bytes(b"\x1bc\x1b[3J");
+    }
 
+    fn resize(&mut self, size: Size) {
+        // Stick to the bottom if we were following the output,
+        // otherwise keep the topmost visible line in place.
+        let follow_output = self.scroll_offset == self.max_scroll();
+        let top_row = self.scroll_offset;
+
+        // wezterm rewraps the lines, pushes lines which no longer fit into the scrollback
+        // and clamps the cursor to the new dimensions.
+        // Size reports for `CSI 18 t` are answered by wezterm from these dimensions as well.
         self.terminal.resize(TerminalSize {
             cols: size.cols,
             rows: size.rows,
             ..Default::default()
         });
         self.size = size;
-        self.update_scroll(new_scroll);
+
+        if follow_output {
+            self.update_scroll(self.max_scroll());
+        } else {
+            self.update_scroll(top_row);
+        }
     }
 
     fn press_key(
         &mut self,
         key: iced::keyboard::Key,
         modifiers: iced::keyboard::Modifiers,
+        location: iced::keyboard::Location,
     ) -> Option<Vec<u8>> {
-        if let Some((key, modifiers)) = transform_key(key, modifiers) {
+        if self.application_keypad
+            && location == iced::keyboard::Location::Numpad
+            && modifiers.is_empty()
+            && let Some(sequence) = application_keypad_sequence(&key)
+        {
+            let _ = self.input.send(sequence.to_vec());
+            self.update_scroll(self.max_scroll());
+        } else if let Some((key, modifiers)) = transform_key(key, modifiers) {
             let _ = self.terminal.key_down(key, modifiers);
             self.update_scroll(self.max_scroll());
         }
@@ -170,6 +410,11 @@ impl TerminalGrid for WeztermGrid {
         None
     }
 
+    fn send_input(&mut self, input: Vec<u8>) {
+        let _ = self.input.send(input);
+        self.update_scroll(self.max_scroll());
+    }
+
     fn scroll(&mut self, lines: isize) {
         self.update_scroll(self.scroll_offset.saturating_add_signed(-lines));
     }
@@ -178,6 +423,55 @@ impl TerminalGrid for WeztermGrid {
         self.update_scroll(self.min_scroll() + y);
     }
 
+    fn scroll_to_bottom(&mut self) {
+        self.update_scroll(self.max_scroll());
+    }
+
+    fn jump_to_prompt(&mut self, previous: bool) -> bool {
+        let prompts = self.prompt_rows();
+        if prompts.is_empty() {
+            return false;
+        }
+
+        let target = if previous {
+            prompts.iter().rev().find(|row| **row < self.scroll_offset)
+        } else {
+            prompts.iter().find(|row| **row > self.scroll_offset)
+        };
+        match target {
+            Some(row) => self.update_scroll(*row),
+            // the current prompt is below the last marked one
+            None if !previous => self.update_scroll(self.max_scroll()),
+            None => {}
+        }
+        true
+    }
+
+    fn scroll_to_last_prompt(&mut self) -> bool {
+        let prompts = self.prompt_rows();
+        let Some(last) = prompts.last() else {
+            return false;
+        };
+
+        // the last prompt usually waits for the next command, so prefer the one of the last output
+        let last_output = self.terminal.get_semantic_zones().ok().and_then(|zones| {
+            zones
+                .iter()
+                .rev()
+                .find(|zone| zone.semantic_type == wezterm_term::SemanticType::Output)
+                .map(|zone| zone.start_y.max(0) as usize)
+        });
+        let row = last_output
+            .and_then(|output| prompts.iter().rev().find(|row| **row <= output))
+            .unwrap_or(last);
+        self.update_scroll(*row);
+        true
+    }
+
+    fn new_lines_below(&self) -> usize {
+        self.new_lines_below
+    }
+
     fn get_scroll(&self) -> usize {
         self.scroll_offset - self.min_scroll()
     }
@@ -186,6 +480,15 @@ impl TerminalGrid for WeztermGrid {
         self.terminal.screen().scrollback_rows()
     }
 
+    fn set_frozen(&mut self, frozen: bool) {
+        if frozen {
+            let following = self.scroll_offset == self.max_scroll();
+            self.frozen.get_or_insert(following);
+        } else if self.frozen.take() == Some(true) {
+            self.update_scroll(self.max_scroll());
+        }
+    }
+
     fn start_selection(&mut self, start: VisiblePosition) {
         if let Some(invalidate) = self.selection.start(start) {
             self.invalidate_lines(invalidate);
@@ -202,6 +505,32 @@ impl TerminalGrid for WeztermGrid {
         self.selection.finish()
     }
 
+    fn select_last_output(&mut self) -> bool {
+        let Ok(zones) = self.terminal.get_semantic_zones() else {
+            return false;
+        };
+        let Some(output) = zones
+            .iter()
+            .rev()
+            .find(|zone| zone.semantic_type == wezterm_term::SemanticType::Output)
+        else {
+            return false;
+        };
+
+        let start = SelectionPosition {
+            x: output.start_x,
+            y: output.start_y.max(0) as usize,
+        };
+        let end = SelectionPosition {
+            x: output.end_x,
+            y: output.end_y.max(0) as usize,
+        };
+        if let Some(invalidate) = self.selection.select(start, end) {
+            self.invalidate_lines(invalidate);
+        }
+        true
+    }
+
     fn currently_selecting(&self) -> bool {
         self.selection.is_active()
     }
@@ -216,11 +545,11 @@ impl TerminalGrid for WeztermGrid {
 
         for (offset, line) in self.screen_lines(range.clone()).iter().enumerate() {
             let index = range.start + offset;
-            for (cell_index, cell) in line.visible_cells().enumerate() {
+            for cell in line.visible_cells() {
                 if is_selected(
                     &selection,
                     SelectionPosition {
-                        x: cell_index,
+                        x: cell.cell_index(),
                         y: index,
                     },
                 ) {
@@ -239,10 +568,27 @@ impl TerminalGrid for WeztermGrid {
         }
     }
 
+    fn set_focused(&mut self, focused: bool) {
+        if self.focused != focused {
+            self.focused = focused;
+            // only reports the change if the application enabled focus tracking
+            self.terminal.focus_changed(focused);
+        }
+    }
+
+    fn is_focused(&self) -> bool {
+        self.focused
+    }
+
     fn get_title(&self) -> &str {
         self.terminal.get_title()
     }
 
+    fn has_title(&self) -> bool {
+        let title = self.terminal.get_title();
+        !title.is_empty() && title != self.initial_title
+    }
+
     fn get_size(&self) -> Size {
         self.size
     }
@@ -251,12 +597,122 @@ impl TerminalGrid for WeztermGrid {
         let pos = self.terminal.cursor_pos();
         let y = (pos.y as usize) + self.inverse_offset();
 
-        if y < self.size.rows && pos.visibility == CursorVisibility::Visible {
+        if y < self.size.rows
+            && pos.x < self.size.cols
+            && pos.visibility == CursorVisibility::Visible
+        {
             Some(VisiblePosition { x: pos.x, y })
         } else {
             None
         }
     }
+
+    fn is_alt_screen(&self) -> bool {
+        self.terminal.is_alt_screen_active()
+    }
+
+    fn cursor_color(&self) -> Option<iced::Color> {
+        // OSC 12 changes the cursor color of the palette, OSC 112 resets it to the default
+        let cursor_bg = self.terminal.palette().cursor_bg;
+        if cursor_bg == self.default_cursor_color {
+            return None;
+        }
+
+        let (r, g, b, a) = cursor_bg.to_tuple_rgba();
+        Some(iced::Color::from_rgba(r, g, b, a))
+    }
+
+    fn reverse_video(&self) -> bool {
+        self.terminal.get_reverse_video()
+    }
+
+    fn visible_cell(&self, position: &VisiblePosition) -> Option<String> {
+        let row = self.scroll_offset + position.y;
+        let line = self.screen_lines(row..row + 1).pop()?;
+
+        cell_in_column(&line, position.x)
+    }
+
+    fn visible_line(&self, position: &VisiblePosition) -> Option<(String, usize)> {
+        let row = self.scroll_offset + position.y;
+        let line = self.screen_lines(row..row + 1).pop()?;
+
+        let mut text = String::new();
+        let mut column = None;
+        for cell in line.visible_cells() {
+            if column.is_none() && position.x < cell.cell_index() + cell.width() {
+                column = Some(text.chars().count());
+            }
+            text.push_str(cell.str());
+        }
+        let column = column.unwrap_or_else(|| text.chars().count());
+
+        Some((text, column))
+    }
+
+    fn screen_contents(&self) -> String {
+        let mut contents = String::new();
+
+        for line in self.screen_lines(self.screen_range()) {
+            contents.push_str(line.as_str().trim_end());
+            contents.push('\n');
+        }
+
+        contents
+    }
+
+    fn cell_at(&self, row: usize, col: usize) -> Option<String> {
+        if row >= self.size.rows {
+            return None;
+        }
+
+        let stable_row = self.screen_range().start + row;
+        let line = self.screen_lines(stable_row..stable_row + 1).pop()?;
+
+        cell_in_column(&line, col)
+    }
+}
+
+fn is_primary_device_attributes(sequence: &Sequence) -> bool {
+    matches!(sequence, Sequence::Csi { params, action: 'c' } if matches!(params.as_str(), "" | "0"))
+}
+
+/// The text of the cell covering the column.
+/// Both halves of a wide character return the character, as wezterm only stores a spacer in the right half.
+fn cell_in_column(line: &wezterm_term::Line, column: usize) -> Option<String> {
+    line.visible_cells()
+        .find(|cell| cell.cell_index() <= column && column < cell.cell_index() + cell.width())
+        .map(|cell| cell.str().to_string())
+}
+
+/// The sequences sent by the numpad in application keypad mode
+fn application_keypad_sequence(key: &iced::keyboard::Key) -> Option<&'static [u8]> {
+    let sequence: &[u8] = match key {
+        iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter) => b"\x1bOM",
+        iced::keyboard::Key::Character(c) => match c.as_str() {
+            "0" => b"\x1bOp",
+            "1" => b"\x1bOq",
+            "2" => b"\x1bOr",
+            "3" => b"\x1bOs",
+            "4" => b"\x1bOt",
+            "5" => b"\x1bOu",
+            "6" => b"\x1bOv",
+            "7" => b"\x1bOw",
+            "8" => b"\x1bOx",
+            "9" => b"\x1bOy",
+            "*" => b"\x1bOj",
+            "+" => b"\x1bOk",
+            "," => b"\x1bOl",
+            "-" => b"\x1bOm",
+            "." => b"\x1bOn",
+            "/" => b"\x1bOo",
+            "=" => b"\x1bOX",
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(sequence)
 }
 
 fn transform_key(
@@ -1,11 +1,12 @@
 use std::{collections::VecDeque, fmt::Debug, ops::Range};
 
 use iced::{advanced::text, widget::text::Span};
-use wezterm_term::{CellAttributes, PhysRowIndex, Underline};
+use termwiz::color::ColorAttribute;
+use wezterm_term::{CellAttributes, Intensity, PhysRowIndex, Underline};
 
 use crate::{
     Style,
-    terminal_grid::PreRenderer,
+    terminal_grid::{PreRenderer, SpanUnderline, TerminalGrid, UnderlineStyle},
     wezterm::{
         WeztermGrid,
         selection::{SelectionPosition, is_maybe_selected},
@@ -18,6 +19,8 @@ pub struct WeztermPreRenderer<R: text::Renderer> {
     cache_rows: VecDeque<ParagraphRow<R>>,
     style: Style,
     visible_cache_range: Range<PhysRowIndex>,
+    /// The reverse video mode the cache was rendered with
+    reverse_video: bool,
 }
 
 impl<R: text::Renderer> WeztermPreRenderer<R> {
@@ -28,8 +31,14 @@ impl<R: text::Renderer> WeztermPreRenderer<R> {
             cache_rows: VecDeque::new(),
             style,
             visible_cache_range: 0..0,
+            reverse_video: false,
         }
     }
+
+    /// Only applies to rows rendered afterwards, the cache has to be cleared
+    pub(crate) fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
 }
 
 impl<R> PreRenderer<R> for WeztermPreRenderer<R>
@@ -47,6 +56,12 @@ where
     }
 
     fn update(&mut self, grid: &Self::Grid, renderer: &R) {
+        let reverse_video = grid.reverse_video();
+        if reverse_video != self.reverse_video {
+            self.clear_cache();
+            self.reverse_video = reverse_video;
+        }
+
         let screen = grid.terminal.screen();
 
         let range = grid.scroll_offset..grid.scroll_offset + screen.physical_rows;
@@ -116,19 +131,22 @@ where
             let mut current_text = String::new();
             let mut current_attrs = CellAttributes::default();
             let mut spans: Vec<Span<(), R::Font>> = Vec::new();
+            let mut underlines = Vec::new();
 
-            for (cell_index, cell) in line.visible_cells().enumerate() {
+            for cell in line.visible_cells() {
                 let cell_selected = is_maybe_selected(
                     &selection,
                     SelectionPosition {
-                        x: cell_index,
+                        x: cell.cell_index(),
                         y: index,
                     },
                 );
                 if cell.attrs() != &current_attrs || is_current_selected != cell_selected {
                     push_span(
                         &self.style,
+                        reverse_video,
                         &mut spans,
+                        &mut underlines,
                         current_text,
                         current_attrs,
                         is_current_selected,
@@ -143,7 +161,9 @@ where
 
             push_span(
                 &self.style,
+                reverse_video,
                 &mut spans,
+                &mut underlines,
                 current_text,
                 current_attrs,
                 is_current_selected,
@@ -164,7 +184,7 @@ where
                     hint_factor: None,
                 };
                 let paragraph = iced::advanced::text::Paragraph::with_spans(text);
-                Some((paragraph, spans))
+                Some((paragraph, spans, underlines))
             } else {
                 None
             };
@@ -176,20 +196,31 @@ where
 
     fn visible_rows<'a>(
         &'a self,
-    ) -> impl Iterator<Item = Option<(&'a R::Paragraph, &'a [text::Span<'a, (), R::Font>])>> {
+    ) -> impl Iterator<
+        Item = Option<(
+            &'a R::Paragraph,
+            &'a [text::Span<'a, (), R::Font>],
+            &'a [SpanUnderline],
+        )>,
+    > {
         self.cache_rows
             .range(self.visible_cache_range.clone())
             .map(|row| {
                 row.cached
                     .as_ref()
-                    .map(|cached| (&cached.0, cached.1.as_slice()))
+                    .map(|cached| (&cached.0, cached.1.as_slice(), cached.2.as_slice()))
             })
     }
 }
 
-fn push_span<Font>(
+/// Opacity of faint text
+const FAINT_ALPHA: f32 = 0.6;
+
+fn push_span<Font: From<iced::Font>>(
     style: &Style,
+    reverse_video: bool,
     spans: &mut Vec<Span<(), Font>>,
+    underlines: &mut Vec<SpanUnderline>,
     text: String,
     attributes: CellAttributes,
     is_current_selected: bool,
@@ -198,32 +229,167 @@ fn push_span<Font>(
         return;
     }
 
+    let bold = attributes.intensity() == Intensity::Bold;
     let mut background = style.get_color(attributes.background());
-    let mut foreground = style.get_color(attributes.foreground());
+    // bold-is-bright, basic colors 0-7 become their bright variants 8-15
+    let brightened = bold
+        && style.bold_is_bright
+        && matches!(attributes.foreground(), ColorAttribute::PaletteIndex(index) if index < 8);
+    let mut foreground = match attributes.foreground() {
+        ColorAttribute::PaletteIndex(index) if brightened => {
+            Some(style.palette.0[index as usize + 8])
+        }
+        color => style.get_color(color),
+    };
 
     // Apply reverse colors for original cell attributes
     if attributes.reverse() != is_current_selected {
         (background, foreground) = (foreground, background);
+        let (default_background, default_foreground) = if reverse_video {
+            (style.foreground_color, style.background_color)
+        } else {
+            (style.background_color, style.foreground_color)
+        };
         if foreground.is_none() {
-            foreground = Some(style.background_color)
+            foreground = Some(default_background)
         }
         if background.is_none() {
-            background = Some(style.foreground_color)
+            background = Some(default_foreground)
+        }
+    }
+
+    // faint text (SGR 2)
+    if attributes.intensity() == Intensity::Half {
+        let default_foreground = if reverse_video {
+            style.background_color
+        } else {
+            style.foreground_color
+        };
+        foreground = Some(
+            foreground
+                .unwrap_or(default_foreground)
+                .scale_alpha(FAINT_ALPHA),
+        );
+    }
+
+    // only colors set by the application can be hard to read, faint text included
+    if style.minimum_contrast > 1.0 && (foreground.is_some() || background.is_some()) {
+        let (default_background, default_foreground) = if reverse_video {
+            (style.foreground_color, style.background_color)
+        } else {
+            (style.background_color, style.foreground_color)
+        };
+        let current = foreground.unwrap_or(default_foreground);
+        let adjusted = ensure_contrast(
+            current,
+            background.unwrap_or(default_background),
+            style.minimum_contrast,
+        );
+        if adjusted != current {
+            foreground = Some(adjusted);
         }
     }
 
-    let span = iced::advanced::text::Span::new(text)
+    // drawn by the widget, as paragraphs only know plain underlines in the text color
+    let underline = match attributes.underline() {
+        Underline::None => None,
+        Underline::Single => Some(UnderlineStyle::Single),
+        Underline::Double => Some(UnderlineStyle::Double),
+        Underline::Curly => Some(UnderlineStyle::Curly),
+        Underline::Dotted => Some(UnderlineStyle::Dotted),
+        Underline::Dashed => Some(UnderlineStyle::Dashed),
+    };
+    if let Some(underline) = underline {
+        underlines.push(SpanUnderline {
+            span: spans.len(),
+            style: underline,
+            color: style.get_color(attributes.underline_color()),
+        });
+    }
+
+    let mut span = iced::advanced::text::Span::new(text)
         .color_maybe(foreground)
-        .background_maybe(background)
-        .underline(attributes.underline() != Underline::None);
+        .background_maybe(background);
+    // the only hint of boldness left if the color didn't change
+    if bold && !brightened {
+        span = span.font(iced::Font {
+            weight: iced::font::Weight::Bold,
+            ..style.font
+        });
+    }
 
     spans.push(span);
 }
 
+/// Moves the foreground towards white or black until it reaches the minimum contrast ratio
+/// to the background, as defined by WCAG.
+/// A translucent foreground, e.g. of faint text, is rated as drawn on top of the background.
+fn ensure_contrast(foreground: iced::Color, background: iced::Color, minimum: f32) -> iced::Color {
+    let background_luminance = relative_luminance(background);
+    let luminance = |color: iced::Color| relative_luminance(blend(color, background));
+    if contrast_ratio(luminance(foreground), background_luminance) >= minimum {
+        return foreground;
+    }
+
+    // whichever direction allows for more contrast
+    let target =
+        if contrast_ratio(1.0, background_luminance) >= contrast_ratio(0.0, background_luminance) {
+            iced::Color::WHITE
+        } else {
+            iced::Color::BLACK
+        };
+    let mix = |amount: f32| iced::Color {
+        r: foreground.r + (target.r - foreground.r) * amount,
+        g: foreground.g + (target.g - foreground.g) * amount,
+        b: foreground.b + (target.b - foreground.b) * amount,
+        a: foreground.a,
+    };
+
+    // the least change that is readable, or the target itself if even that isn't enough
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..10 {
+        let amount = (low + high) / 2.0;
+        if contrast_ratio(luminance(mix(amount)), background_luminance) >= minimum {
+            high = amount;
+        } else {
+            low = amount;
+        }
+    }
+    mix(high)
+}
+
+/// The foreground drawn on top of an opaque background
+fn blend(foreground: iced::Color, background: iced::Color) -> iced::Color {
+    let alpha = foreground.a;
+    let channel =
+        |foreground: f32, background: f32| foreground * alpha + background * (1.0 - alpha);
+    iced::Color::from_rgb(
+        channel(foreground.r, background.r),
+        channel(foreground.g, background.g),
+        channel(foreground.b, background.b),
+    )
+}
+
+fn relative_luminance(color: iced::Color) -> f32 {
+    let linear = |channel: f32| {
+        if channel <= 0.03928 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
+
+fn contrast_ratio(luminance: f32, other: f32) -> f32 {
+    (luminance.max(other) + 0.05) / (luminance.min(other) + 0.05)
+}
+
 pub struct ParagraphRow<R: text::Renderer> {
     pub cached: Option<(
         R::Paragraph,
         Vec<iced::advanced::text::Span<'static, (), R::Font>>,
+        Vec<SpanUnderline>,
     )>,
     // pub paragraph: R::Paragraph,
     // pub spans: Vec<iced::advanced::text::Span<'static, (), R::Font>>,
@@ -246,3 +412,117 @@ impl<R: text::Renderer> Debug for ParagraphRow<R> {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(style: &Style, attributes: &CellAttributes) -> Span<'static, (), iced::Font> {
+        let mut spans = Vec::new();
+        push_span(
+            style,
+            false,
+            &mut spans,
+            &mut Vec::new(),
+            "text".to_string(),
+            attributes.clone(),
+            false,
+        );
+        spans.pop().unwrap()
+    }
+
+    #[test]
+    fn bold_basic_color_is_bright() {
+        let style = Style::default();
+        let mut attributes = CellAttributes::default();
+        attributes
+            .set_intensity(Intensity::Bold)
+            .set_foreground(ColorAttribute::PaletteIndex(1));
+
+        let span = span(&style, &attributes);
+        assert_eq!(span.color, Some(style.palette.0[9]));
+        assert_eq!(span.font, None);
+    }
+
+    #[test]
+    fn bold_without_basic_color_keeps_bold_font() {
+        let style = Style::default();
+        let mut attributes = CellAttributes::default();
+        attributes.set_intensity(Intensity::Bold);
+        assert!(span(&style, &attributes).font.is_some());
+
+        attributes.set_foreground(ColorAttribute::PaletteIndex(100));
+        let span = span(&style, &attributes);
+        assert_eq!(span.color, Some(style.palette.0[100]));
+        assert!(span.font.is_some());
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn wcag_references() {
+        assert_close(relative_luminance(iced::Color::WHITE), 1.0);
+        assert_close(relative_luminance(iced::Color::BLACK), 0.0);
+        assert_close(contrast_ratio(1.0, 0.0), 21.0);
+
+        // #767676 is the lightest gray passing AA on white, #777777 just fails it
+        let white = relative_luminance(iced::Color::WHITE);
+        let passing = relative_luminance(iced::Color::from_rgb8(0x76, 0x76, 0x76));
+        let failing = relative_luminance(iced::Color::from_rgb8(0x77, 0x77, 0x77));
+        assert_close(contrast_ratio(passing, white), 4.54);
+        assert_close(contrast_ratio(failing, white), 4.48);
+    }
+
+    #[test]
+    fn contrast_is_ensured() {
+        let gray = iced::Color::from_rgb8(0x77, 0x77, 0x77);
+        assert_eq!(ensure_contrast(gray, iced::Color::WHITE, 4.0), gray);
+
+        let adjusted = ensure_contrast(gray, iced::Color::WHITE, 4.5);
+        assert!(adjusted.r < gray.r);
+        let ratio = contrast_ratio(
+            relative_luminance(adjusted),
+            relative_luminance(iced::Color::WHITE),
+        );
+        assert!((4.5..4.6).contains(&ratio), "{ratio}");
+    }
+
+    #[test]
+    fn faint_text_keeps_contrast() {
+        let style = Style::default()
+            .background_color(iced::Color::BLACK)
+            .foreground_color(iced::Color::from_rgb(0.7, 0.7, 0.7))
+            .minimum_contrast(7.0);
+        let mut attributes = CellAttributes::default();
+        attributes.set_intensity(Intensity::Half);
+
+        let color = span(&style, &attributes).color.unwrap();
+        assert_close(color.a, FAINT_ALPHA);
+        let drawn = blend(color, iced::Color::BLACK);
+        let ratio = contrast_ratio(
+            relative_luminance(drawn),
+            relative_luminance(iced::Color::BLACK),
+        );
+        assert!(ratio >= 7.0, "{ratio}");
+    }
+
+    #[test]
+    fn faint_text_is_dimmed() {
+        let style = Style::default();
+        let mut attributes = CellAttributes::default();
+        assert_eq!(span(&style, &attributes).color, None);
+
+        attributes.set_intensity(Intensity::Half);
+        let dimmed = span(&style, &attributes).color.unwrap();
+        assert_eq!(dimmed, style.foreground_color.scale_alpha(FAINT_ALPHA));
+
+        attributes.set_foreground(ColorAttribute::PaletteIndex(2));
+        let dimmed = span(&style, &attributes).color.unwrap();
+        assert_eq!(dimmed, style.palette.0[2].scale_alpha(FAINT_ALPHA));
+    }
+}
@@ -108,6 +108,30 @@ impl SelectionState {
         }
     }
 
+    /// Replaces the selection with a finished one, e.g. computed from the content
+    #[must_use]
+    pub fn select(
+        &mut self,
+        start: SelectionPosition,
+        end: SelectionPosition,
+    ) -> Option<Range<PhysRowIndex>> {
+        let mut invalidate = start.y.min(end.y)..start.y.max(end.y) + 1;
+        if let Some(previous) = self.get_selection() {
+            invalidate.start = invalidate.start.min(previous.start.y);
+            invalidate.end = invalidate.end.max(previous.end.y + 1);
+        }
+        self.step = SelectionStep::Selected { start, end };
+        Some(invalidate)
+    }
+
+    /// Drops the selection, e.g. because the selected lines are gone
+    #[must_use]
+    pub fn clear(&mut self) -> Option<Range<PhysRowIndex>> {
+        let selection = self.get_selection()?;
+        self.step = SelectionStep::None;
+        Some(selection.start.y..selection.end.y + 1)
+    }
+
     pub fn finish(&mut self) {
         match &self.step {
             SelectionStep::Selecting { start, end } => {
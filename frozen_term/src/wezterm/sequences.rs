@@ -0,0 +1,118 @@
+/// Finds escape sequences in the output which wezterm ignores, doesn't expose
+/// or which need handling on top of what wezterm does.
+/// The state is kept across chunks, so sequences may be split between them.
+#[derive(Debug, Default)]
+pub struct SequenceScanner {
+    state: ScanState,
+    buffer: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum ScanState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    Osc,
+    /// An escape inside an OSC, which might be the start of its terminator
+    OscEscape,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sequence {
+    /// A CSI sequence with its parameters and final byte, e.g. `CSI 22 ; 0 t`
+    Csi { params: String, action: char },
+    /// An escape sequence without parameters, e.g. `ESC =`
+    Esc(char),
+}
+
+/// Final bytes of the CSI sequences reported by the scanner
+const CSI_ACTIONS: &[u8] = b"tJc";
+/// Final bytes of the escape sequences reported by the scanner: DECKPAM, DECKPNM and RIS
+const ESC_ACTIONS: &[u8] = b"=>c";
+/// Longer sequences are dropped, they can't be meaningful
+const MAX_SEQUENCE_LENGTH: usize = 64;
+
+impl SequenceScanner {
+    /// Returns each found sequence along with the offset right after it
+    pub fn scan(&mut self, bytes: &[u8]) -> Vec<(usize, Sequence)> {
+        let mut sequences = Vec::new();
+
+        for (index, &byte) in bytes.iter().enumerate() {
+            self.state = match (self.state, byte) {
+                // CAN and SUB abort any sequence
+                (_, 0x18 | 0x1a) => ScanState::Ground,
+                (ScanState::Osc, 0x07) => ScanState::Ground,
+                (ScanState::OscEscape, b'\\') => ScanState::Ground,
+                (ScanState::Osc | ScanState::OscEscape, 0x1b) => ScanState::OscEscape,
+                (ScanState::Osc | ScanState::OscEscape, _) => ScanState::Osc,
+                (_, 0x1b) => ScanState::Escape,
+                (ScanState::Escape, b'[') => {
+                    self.buffer.clear();
+                    ScanState::Csi
+                }
+                (ScanState::Escape, b']') => ScanState::Osc,
+                (ScanState::Escape, _) if ESC_ACTIONS.contains(&byte) => {
+                    sequences.push((index + 1, Sequence::Esc(byte as char)));
+                    ScanState::Ground
+                }
+                (ScanState::Csi, 0x20..=0x3f) => {
+                    if self.buffer.len() < MAX_SEQUENCE_LENGTH {
+                        self.buffer.push(byte);
+                        ScanState::Csi
+                    } else {
+                        ScanState::Ground
+                    }
+                }
+                (ScanState::Csi, 0x40..=0x7e) => {
+                    if CSI_ACTIONS.contains(&byte) {
+                        sequences.push((
+                            index + 1,
+                            Sequence::Csi {
+                                params: String::from_utf8_lossy(&self.buffer).into_owned(),
+                                action: byte as char,
+                            },
+                        ));
+                    }
+                    ScanState::Ground
+                }
+                // control characters are executed in the middle of a CSI
+                (ScanState::Csi, 0x00..=0x1f) => ScanState::Csi,
+                _ => ScanState::Ground,
+            };
+        }
+
+        sequences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_escape_sequences() {
+        let mut scanner = SequenceScanner::default();
+        assert_eq!(scanner.scan(b"a\x1b"), []);
+        assert_eq!(scanner.scan(b"=b\x1b[22"), [(1, Sequence::Esc('='))]);
+        assert_eq!(
+            scanner.scan(b";0t"),
+            [(
+                3,
+                Sequence::Csi {
+                    params: "22;0".to_string(),
+                    action: 't'
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn escapes_inside_osc_are_ignored() {
+        let mut scanner = SequenceScanner::default();
+        assert_eq!(
+            scanner.scan(b"\x1b]2;\x1b=\x07\x1b>"),
+            [(9, Sequence::Esc('>'))]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Snapshots of the grid, to reproduce parser behavior in tests and bug reports.
+//!
+//! A snapshot is a header with the grid size, followed by escape sequences which rebuild
+//! the scrollback, the screen and the cursor position when fed into an empty grid.
+//! Only the active screen is included, so a snapshot taken inside e.g. vim has no scrollback.
+//! The format is only meant to round-trip within the same version of frozen_term.
+
+use std::io::Write;
+
+use termwiz::color::ColorAttribute;
+use tokio_stream::Stream;
+use wezterm_term::{CellAttributes, Intensity, Underline};
+
+use crate::{
+    terminal_grid::{Size, TerminalGrid},
+    wezterm::{GridEvent, WeztermGrid},
+};
+
+const HEADER: &str = "frozen_term snapshot";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("the data is no frozen_term snapshot")]
+    MissingHeader,
+    #[error("invalid grid size in snapshot header")]
+    InvalidSize,
+}
+
+impl WeztermGrid {
+    pub fn dump(&self) -> Vec<u8> {
+        let mut snapshot =
+            format!("{} {}x{}\n", HEADER, self.size.cols, self.size.rows).into_bytes();
+
+        let lines = self.screen_lines(self.min_scroll()..self.screen_range().end);
+        let mut current_attrs = CellAttributes::default();
+        for (index, line) in lines.iter().enumerate() {
+            if index > 0 && !lines[index - 1].last_cell_was_wrapped() {
+                snapshot.extend_from_slice(b"\r\n");
+            }
+
+            for cell in line.visible_cells() {
+                if cell.attrs() != &current_attrs {
+                    write_sgr(&mut snapshot, cell.attrs());
+                    current_attrs = cell.attrs().clone();
+                }
+                snapshot.extend_from_slice(cell.str().as_bytes());
+            }
+        }
+
+        let cursor = self.terminal.cursor_pos();
+        let _ = write!(snapshot, "\x1b[0m\x1b[{};{}H", cursor.y + 1, cursor.x + 1);
+
+        snapshot
+    }
+
+    pub fn load(snapshot: &[u8]) -> Result<(Self, impl Stream<Item = GridEvent>), SnapshotError> {
+        let newline = snapshot
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or(SnapshotError::MissingHeader)?;
+        let header =
+            std::str::from_utf8(&snapshot[..newline]).map_err(|_| SnapshotError::MissingHeader)?;
+        let size = header
+            .strip_prefix(HEADER)
+            .ok_or(SnapshotError::MissingHeader)?
+            .trim();
+
+        let (cols, rows) = size.split_once('x').ok_or(SnapshotError::InvalidSize)?;
+        let size = Size {
+            cols: cols.parse().map_err(|_| SnapshotError::InvalidSize)?,
+            rows: rows.parse().map_err(|_| SnapshotError::InvalidSize)?,
+        };
+        if size.cols == 0 || size.rows == 0 {
+            return Err(SnapshotError::InvalidSize);
+        }
+
+        let (mut grid, events) = WeztermGrid::new();
+        grid.resize(size);
+        grid.advance_bytes(&snapshot[newline + 1..]);
+
+        Ok((grid, events))
+    }
+}
+
+/// Writes an SGR sequence setting exactly the given attributes
+fn write_sgr(out: &mut Vec<u8>, attrs: &CellAttributes) {
+    out.extend_from_slice(b"\x1b[0");
+    match attrs.intensity() {
+        Intensity::Normal => {}
+        Intensity::Bold => out.extend_from_slice(b";1"),
+        Intensity::Half => out.extend_from_slice(b";2"),
+    }
+    if attrs.italic() {
+        out.extend_from_slice(b";3");
+    }
+    match attrs.underline() {
+        Underline::None => {}
+        Underline::Single => out.extend_from_slice(b";4"),
+        Underline::Double => out.extend_from_slice(b";21"),
+        Underline::Curly => out.extend_from_slice(b";4:3"),
+        Underline::Dotted => out.extend_from_slice(b";4:4"),
+        Underline::Dashed => out.extend_from_slice(b";4:5"),
+    }
+    if attrs.reverse() {
+        out.extend_from_slice(b";7");
+    }
+    if attrs.invisible() {
+        out.extend_from_slice(b";8");
+    }
+    if attrs.strikethrough() {
+        out.extend_from_slice(b";9");
+    }
+    write_color(out, 38, attrs.foreground());
+    write_color(out, 48, attrs.background());
+    write_color(out, 58, attrs.underline_color());
+    out.push(b'm');
+}
+
+fn write_color(out: &mut Vec<u8>, kind: u8, color: ColorAttribute) {
+    let _ = match color {
+        ColorAttribute::Default => Ok(()),
+        ColorAttribute::PaletteIndex(index) => write!(out, ";{};5;{}", kind, index),
+        ColorAttribute::TrueColorWithPaletteFallback(color, _)
+        | ColorAttribute::TrueColorWithDefaultFallback(color) => {
+            let (r, g, b, _) = color.to_srgb_u8();
+            write!(out, ";{};2;{};{};{}", kind, r, g, b)
+        }
+    };
+}
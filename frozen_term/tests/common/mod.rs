@@ -0,0 +1,60 @@
+// not every test file uses every helper
+#![allow(dead_code)]
+
+use frozen_term::{
+    KeyPress, Size,
+    local_terminal::{LocalTerminal, TerminalInput},
+};
+use iced::keyboard::{Key, Location, Modifiers};
+use tokio::sync::mpsc::Receiver;
+
+/// A scripted terminal and whatever it writes to the process
+pub struct Harness {
+    pub terminal: LocalTerminal,
+    input: Receiver<TerminalInput>,
+}
+
+impl Harness {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let (terminal, mut input) = LocalTerminal::scripted(Size { cols, rows });
+        // the initial resize isn't interesting
+        while input.try_recv().is_ok() {}
+        Self { terminal, input }
+    }
+
+    pub fn feed(&mut self, output: &[u8]) {
+        self.terminal.feed_output(output);
+    }
+
+    /// Types the key and returns what it sent to the process
+    pub fn press(&mut self, key: Key, modifiers: Modifiers) -> Vec<u8> {
+        let _ = self
+            .terminal
+            .press_key(KeyPress::new(key, modifiers, Location::Standard));
+        self.terminal.process_events();
+        self.written()
+    }
+
+    /// Types the key into the terminal shown, through its key filter, and returns what it sent
+    pub fn type_key(&mut self, key: Key, modifiers: Modifiers) -> Vec<u8> {
+        self.terminal
+            .type_key(KeyPress::new(key, modifiers, Location::Standard));
+        self.written()
+    }
+
+    /// The bytes written to the process since the last call
+    pub fn written(&mut self) -> Vec<u8> {
+        let mut written = Vec::new();
+        while let Ok(input) = self.input.try_recv() {
+            if let TerminalInput::Input(bytes) = input {
+                written.extend(bytes);
+            }
+        }
+        written
+    }
+
+    /// The visible rows, without the trailing empty ones
+    pub fn screen(&self) -> String {
+        self.terminal.screen_contents().trim_end().to_string()
+    }
+}
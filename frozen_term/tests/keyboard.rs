@@ -0,0 +1,74 @@
+mod common;
+
+use common::Harness;
+use iced::keyboard::{Key, Modifiers, key::Named};
+
+#[test]
+fn cursor_keys() {
+    let keys = [
+        (Named::ArrowUp, 'A'),
+        (Named::ArrowDown, 'B'),
+        (Named::ArrowRight, 'C'),
+        (Named::ArrowLeft, 'D'),
+        (Named::Home, 'H'),
+        (Named::End, 'F'),
+    ];
+
+    let mut harness = Harness::new(20, 5);
+    for (key, final_byte) in keys {
+        let sent = harness.press(Key::Named(key), Modifiers::empty());
+        assert_eq!(sent, format!("\x1b[{final_byte}").as_bytes(), "{key:?}");
+    }
+
+    // application cursor keys (DECCKM)
+    harness.feed(b"\x1b[?1h");
+    for (key, final_byte) in keys {
+        let sent = harness.press(Key::Named(key), Modifiers::empty());
+        assert_eq!(sent, format!("\x1bO{final_byte}").as_bytes(), "{key:?}");
+    }
+
+    // reset by RIS
+    harness.feed(b"\x1bc");
+    let sent = harness.press(Key::Named(Named::ArrowUp), Modifiers::empty());
+    assert_eq!(sent, b"\x1b[A");
+}
+
+#[test]
+fn editing_and_function_keys() {
+    let keys: [(Named, &[u8]); 11] = [
+        (Named::Backspace, b"\x7f"),
+        (Named::Delete, b"\x1b[3~"),
+        (Named::Enter, b"\r"),
+        (Named::Tab, b"\t"),
+        (Named::Escape, b"\x1b"),
+        (Named::PageUp, b"\x1b[5~"),
+        (Named::PageDown, b"\x1b[6~"),
+        (Named::F1, b"\x1bOP"),
+        (Named::F4, b"\x1bOS"),
+        (Named::F5, b"\x1b[15~"),
+        (Named::F12, b"\x1b[24~"),
+    ];
+
+    let mut harness = Harness::new(20, 5);
+    for (key, expected) in keys {
+        let sent = harness.press(Key::Named(key), Modifiers::empty());
+        assert_eq!(sent, expected, "{key:?}");
+    }
+}
+
+#[test]
+fn backspace_sends_bs() {
+    let mut harness = Harness::new(20, 5);
+    harness.terminal.set_backspace(frozen_term::Backspace::Bs);
+    let sent = harness.press(Key::Named(Named::Backspace), Modifiers::empty());
+    assert_eq!(sent, b"\x08");
+}
+
+#[test]
+fn control_letters() {
+    let mut harness = Harness::new(20, 5);
+    for (letter, byte) in ('a'..='z').zip(1u8..) {
+        let sent = harness.press(Key::Character(letter.to_string().into()), Modifiers::CTRL);
+        assert_eq!(sent, [byte], "Ctrl+{letter}");
+    }
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::Harness;
+use frozen_term::Size;
+use iced::keyboard::{Key, Modifiers, key::Named};
+
+#[test]
+fn output_and_replies() {
+    let mut harness = Harness::new(20, 5);
+    harness.feed(b"hello\r\nworld");
+    assert_eq!(harness.screen(), "hello\nworld");
+
+    // cursor position report
+    harness.feed(b"\x1b[6n");
+    assert_eq!(harness.written(), b"\x1b[2;6R");
+}
+
+#[test]
+fn frozen_terminal_answers_queries() {
+    let mut harness = Harness::new(20, 5);
+    harness.terminal.set_frozen(true);
+    harness.feed(b"abc\x1b[6n");
+    assert_eq!(harness.written(), b"\x1b[1;4R");
+
+    harness.terminal.set_frozen(false);
+    assert_eq!(harness.screen(), "abc");
+}
+
+#[test]
+fn control_chars_are_only_displayed() {
+    let mut harness = Harness::new(20, 5);
+    let _ = harness.terminal.set_show_control_chars(true);
+    harness.feed(b"a\x1b[6n\r");
+    harness.feed(b"\nb");
+    // the query is still answered
+    assert_eq!(harness.written(), b"\x1b[1;2R");
+    assert_eq!(harness.screen(), "a^[[6n\nb");
+
+    // the screen was interpreted in the background
+    let _ = harness.terminal.set_show_control_chars(false);
+    assert_eq!(harness.screen(), "a\nb");
+}
+
+#[test]
+fn control_chars_keep_the_key_filter() {
+    let mut harness = Harness::new(20, 5);
+    harness
+        .terminal
+        .set_key_filter(|key: &Key, modifiers: &Modifiers| {
+            modifiers.control() && modifiers.shift() && *key == Key::Character("T".into())
+        });
+    let _ = harness.terminal.set_show_control_chars(true);
+
+    let new_tab = harness.type_key(
+        Key::Character("T".into()),
+        Modifiers::CTRL | Modifiers::SHIFT,
+    );
+    assert_eq!(new_tab, b"");
+
+    // keys are encoded with the modes set by the process, application cursor keys here
+    harness.feed(b"\x1b[?1h");
+    assert_eq!(
+        harness.type_key(Key::Named(Named::ArrowUp), Modifiers::empty()),
+        b"\x1bOA"
+    );
+}
+
+#[test]
+fn device_attributes() {
+    let mut harness = Harness::new(20, 5);
+    harness.feed(b"\x1b[c");
+    assert_eq!(harness.written(), b"\x1b[?65;6;18;22c");
+    harness.feed(b"\x1b[0c");
+    assert_eq!(harness.written(), b"\x1b[?65;6;18;22c");
+
+    // split between chunks
+    harness.feed(b"\x1b[");
+    harness.feed(b"c");
+    assert_eq!(harness.written(), b"\x1b[?65;6;18;22c");
+    assert_eq!(harness.screen(), "");
+
+    harness.feed(b"\x1b[>c");
+    assert_eq!(harness.written(), b"\x1b[>1;277;0c");
+}
+
+#[test]
+fn cursor_position_report() {
+    let mut harness = Harness::new(20, 5);
+    harness.feed(b"\x1b[3;7H\x1b[6n");
+    assert_eq!(harness.written(), b"\x1b[3;7R");
+}
+
+#[test]
+fn resize_keeps_the_cursor_on_its_line() {
+    let mut harness = Harness::new(10, 5);
+    harness.feed(b"L1\r\nL2\r\nL3\r\nL4\r\nL5");
+
+    // lines which no longer fit end up in the scrollback
+    let _ = harness.terminal.resize(Size { cols: 10, rows: 3 });
+    assert_eq!(harness.screen(), "L3\nL4\nL5");
+    harness.feed(b"\x1b[6n");
+    assert_eq!(harness.written(), b"\x1b[3;3R");
+
+    let _ = harness.terminal.resize(Size { cols: 10, rows: 5 });
+    let row = harness
+        .screen()
+        .lines()
+        .position(|line| line == "L5")
+        .unwrap();
+    harness.feed(b"\x1b[6n");
+    assert_eq!(harness.written(), format!("\x1b[{};3R", row + 1).as_bytes());
+
+    // nothing was duplicated
+    let dump = String::from_utf8_lossy(&harness.terminal.dump()).into_owned();
+    for line in ["L1", "L2", "L3", "L4", "L5"] {
+        assert_eq!(dump.matches(line).count(), 1, "{dump:?}");
+    }
+}
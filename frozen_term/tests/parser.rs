@@ -0,0 +1,250 @@
+mod common;
+
+use common::Harness;
+
+#[test]
+fn save_and_restore_cursor() {
+    let mut harness = Harness::new(20, 5);
+    harness.feed(b"\x1b[2;3H\x1b7\x1b[5;5H\x1b8\x1b[6n");
+    assert_eq!(harness.written(), b"\x1b[2;3R");
+
+    harness.feed(b"\x1b[3;4H\x1b[s\x1b[H\x1b[uX");
+    assert_eq!(harness.terminal.cell_at(2, 3).as_deref(), Some("X"));
+}
+
+#[test]
+fn save_and_restore_cursor_attributes() {
+    let mut harness = Harness::new(20, 5);
+    harness.feed(b"\x1b[1;31m\x1b7\x1b[0m\x1b8X");
+    let dump = dumped_text(&harness);
+    assert!(dump.contains("\x1b[0;1;38;5;1mX"), "{dump:?}");
+}
+
+#[test]
+fn dec_line_drawing() {
+    let mut harness = Harness::new(20, 3);
+    harness.feed(b"\x1b(0qx\x1b(Bqx");
+    assert_eq!(harness.screen(), "─│qx");
+}
+
+#[test]
+fn autowrap() {
+    let mut harness = Harness::new(5, 3);
+    harness.feed(b"abcdefg");
+    assert_eq!(harness.screen(), "abcde\nfg");
+
+    // DECAWM off overwrites the last column
+    let mut harness = Harness::new(5, 3);
+    harness.feed(b"\x1b[?7labcdefg");
+    assert_eq!(harness.screen(), "abcdg");
+}
+
+fn dumped_text(harness: &Harness) -> String {
+    String::from_utf8_lossy(&harness.terminal.dump()).into_owned()
+}
+
+#[test]
+fn scroll_region_at_the_top_fills_the_scrollback() {
+    let mut harness = Harness::new(10, 5);
+    harness.feed(b"\x1b[1;3rL1\r\nL2\r\nL3\r\nL4\r\nL5");
+    assert_eq!(harness.screen(), "L3\nL4\nL5");
+    let dump = dumped_text(&harness);
+    assert!(dump.contains("L1") && dump.contains("L2"), "{dump:?}");
+}
+
+#[test]
+fn scroll_region_further_down_skips_the_scrollback() {
+    let mut harness = Harness::new(10, 5);
+    harness.feed(b"\x1b[2;4r\x1b[2;1HL1\r\nL2\r\nL3\r\nL4\r\nL5");
+    assert_eq!(harness.screen(), "\nL3\nL4\nL5");
+    let dump = dumped_text(&harness);
+    assert!(!dump.contains("L1") && !dump.contains("L2"), "{dump:?}");
+}
+
+#[test]
+fn origin_mode() {
+    let mut harness = Harness::new(10, 5);
+    harness.feed(b"\x1b[2;4r\x1b[?6h\x1b[HX");
+    assert_eq!(harness.terminal.cell_at(1, 0).as_deref(), Some("X"));
+    // reported relative to the region
+    harness.feed(b"\x1b[6n");
+    assert_eq!(harness.written(), b"\x1b[1;2R");
+
+    // clamped to the region
+    harness.feed(b"\x1b[9;1HY");
+    assert_eq!(harness.terminal.cell_at(3, 0).as_deref(), Some("Y"));
+
+    // resetting the mode homes the cursor to the top of the screen
+    harness.feed(b"\x1b[?6lZ");
+    assert_eq!(harness.terminal.cell_at(0, 0).as_deref(), Some("Z"));
+}
+
+#[test]
+fn alternate_screen_in_the_first_output() {
+    let mut harness = Harness::new(10, 3);
+    harness.feed(b"\x1b[?1049h\x1b[1mtui");
+    assert_eq!(harness.screen(), "tui");
+
+    harness.feed(b"\x1b[?1049l");
+    assert_eq!(harness.screen(), "");
+}
+
+#[test]
+fn size_report() {
+    let mut harness = Harness::new(20, 5);
+    harness.feed(b"\x1b[18t");
+    assert_eq!(harness.written(), b"\x1b[8;5;20t");
+
+    // resize requests are ignored
+    harness.feed(b"\x1b[8;10;40t\x1b[18t");
+    assert_eq!(harness.written(), b"\x1b[8;5;20t");
+}
+
+#[test]
+fn title_stack() {
+    let mut harness = Harness::new(20, 5);
+    harness.feed(b"\x1b]2;shell\x07\x1b[22;0t");
+    harness.feed(b"\x1b]2;editor\x07");
+    assert_eq!(harness.terminal.get_title(), "editor");
+
+    harness.feed(b"\x1b[23;0t");
+    assert_eq!(harness.terminal.get_title(), "shell");
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let mut harness = Harness::new(10, 3);
+    harness.feed(b"one\r\ntwo\r\n\x1b[1;31mthree\x1b[0m\r\n\x1b[4;48;5;200mwrapped past the edge\x1b[0m\r\n\x1b[38;2;1;2;3mfour");
+    harness.feed(b"\x1b[2;3H");
+    let dump = harness.terminal.dump();
+
+    let (loaded, _) = frozen_term::Terminal::load(&dump).unwrap();
+    assert_eq!(loaded.dump(), dump);
+    assert_eq!(loaded.screen_contents(), harness.terminal.screen_contents());
+
+    assert!(frozen_term::Terminal::load(b"no snapshot\n").is_err());
+    assert!(frozen_term::Terminal::load(b"frozen_term snapshot 0x3\n").is_err());
+}
+
+#[test]
+fn faint_text() {
+    let mut harness = Harness::new(20, 3);
+    harness.feed(b"\x1b[2mdim\x1b[22m normal");
+    let dump = String::from_utf8(harness.terminal.dump()).unwrap();
+    assert!(dump.contains("\x1b[0;2mdim\x1b[0m normal"), "{dump:?}");
+}
+
+#[test]
+fn underline_styles() {
+    let mut harness = Harness::new(20, 3);
+    harness.feed(b"\x1b[4:3mcurly\x1b[4:0m plain");
+    let dump = dumped_text(&harness);
+    assert!(dump.contains("\x1b[0;4:3mcurly\x1b[0m plain"), "{dump:?}");
+}
+
+#[test]
+fn underline_colors() {
+    for (sequence, color) in [
+        ("\x1b[4;58:2::255:128:0m", ";58;2;255;128;0m"),
+        ("\x1b[4;58:5:1m", ";58;5;1m"),
+    ] {
+        let mut harness = Harness::new(20, 3);
+        harness.feed(format!("{sequence}colored\x1b[59mdefault").as_bytes());
+        let dump = dumped_text(&harness);
+        let expected = format!("\x1b[0;4{color}colored\x1b[0;4mdefault");
+        assert!(dump.contains(&expected), "{sequence:?}: {dump:?}");
+    }
+}
+
+#[test]
+fn overwriting_half_of_a_wide_character() {
+    let mut harness = Harness::new(10, 3);
+    harness.feed("中".as_bytes());
+    assert_eq!(harness.terminal.cell_at(0, 0).as_deref(), Some("中"));
+    assert_eq!(harness.terminal.cell_at(0, 1).as_deref(), Some("中"));
+
+    // the left half
+    harness.feed(b"\rx");
+    assert_eq!(harness.screen(), "x");
+    assert_ne!(harness.terminal.cell_at(0, 1).as_deref(), Some("中"));
+
+    // the right half
+    harness.feed("\r中\x1b[2Gy".as_bytes());
+    assert_eq!(harness.screen(), " y");
+    assert_ne!(harness.terminal.cell_at(0, 0).as_deref(), Some("中"));
+}
+
+/// Fills a 5x3 screen, then erases with a blue background from the second row, third column
+fn erased(erase: &str) -> Harness {
+    let mut harness = Harness::new(5, 3);
+    harness.feed(b"abcde\r\nfghij\r\nklmno\x1b[2;3H\x1b[44m");
+    harness.feed(erase.as_bytes());
+    harness
+}
+
+const BLUE_BACKGROUND: &str = "48;5;4m";
+
+#[test]
+fn erase_in_display() {
+    for (erase, screen) in [
+        ("\x1b[J", "abcde\nfg"),
+        ("\x1b[0J", "abcde\nfg"),
+        ("\x1b[1J", "\n   ij\nklmno"),
+        ("\x1b[2J", ""),
+    ] {
+        let harness = erased(erase);
+        assert_eq!(harness.screen(), screen, "{erase:?}");
+        assert!(dumped_text(&harness).contains(BLUE_BACKGROUND), "{erase:?}");
+    }
+}
+
+#[test]
+fn erase_scrollback() {
+    let mut harness = Harness::new(5, 3);
+    harness.feed(b"L1\r\nL2\r\nL3\r\nL4\r\nL5");
+    assert!(dumped_text(&harness).contains("L1"));
+
+    harness.feed(b"\x1b[3J");
+    assert_eq!(harness.screen(), "L3\nL4\nL5");
+    let dump = dumped_text(&harness);
+    assert!(!dump.contains("L1") && !dump.contains("L2"), "{dump:?}");
+}
+
+#[test]
+fn erase_in_line() {
+    for (erase, screen) in [
+        ("\x1b[K", "abcde\nfg\nklmno"),
+        ("\x1b[0K", "abcde\nfg\nklmno"),
+        ("\x1b[1K", "abcde\n   ij\nklmno"),
+        ("\x1b[2K", "abcde\n\nklmno"),
+    ] {
+        let harness = erased(erase);
+        assert_eq!(harness.screen(), screen, "{erase:?}");
+        assert!(dumped_text(&harness).contains(BLUE_BACKGROUND), "{erase:?}");
+    }
+}
+
+#[test]
+fn scrollback_size() {
+    let mut harness = Harness::new(10, 3);
+    harness.terminal.set_scrollback_size(2);
+    for line in 1..=10 {
+        harness.feed(format!("L{line}\r\n").as_bytes());
+    }
+    let dump = dumped_text(&harness);
+    assert!(dump.contains("L7") && !dump.contains("L6"), "{dump:?}");
+}
+
+#[test]
+fn alternate_screen_keeps_the_scrollback() {
+    let mut harness = Harness::new(10, 3);
+    harness.feed(b"p1\r\np2\r\np3\r\np4\r\n$ ");
+    let before = harness.terminal.dump();
+
+    harness.feed(b"\x1b[?1049h");
+    for line in 0..1000 {
+        harness.feed(format!("tui {line}\r\n").as_bytes());
+    }
+    harness.feed(b"\x1b[?1049l");
+    assert_eq!(harness.terminal.dump(), before);
+}